@@ -0,0 +1,30 @@
+//! Tallies votes whose archive memos are arbitrary strings, which must never
+//! panic however malformed the memos are.
+//!
+//! Run with `cargo fuzz run tally_memo` from the `server` directory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mina_ocv::{BlockStatus, RankedVote, Vote, Wrapper, decode_memo};
+
+fuzz_target!(|memos: Vec<String>| {
+  let votes: Vec<Vote> = memos
+    .iter()
+    .enumerate()
+    .map(|(i, memo)| {
+      let _ = decode_memo(memo);
+      Vote::new(format!("{}", i % 3), format!("{i}"), memo.clone(), i as i64, BlockStatus::Pending, i as i64, 0)
+    })
+    .collect();
+  let ranked: Vec<RankedVote> = votes
+    .iter()
+    .map(|vote| {
+      RankedVote::new(&vote.account, &vote.hash, &vote.memo, vote.height, vote.status, vote.timestamp, vote.nonce)
+    })
+    .collect();
+
+  let _ = Wrapper(votes.clone()).process_with_rejections("cftest-2", 10, 1);
+  let _ = Wrapper(votes).process_mep_with_rejections(1, 1, 10, 1);
+  let _ = Wrapper(ranked).process_ranked_vote_with_rejections(1, 10, 1);
+});
@@ -1,61 +1,332 @@
-use anyhow::{Context, Result};
+use std::{
+  collections::HashMap,
+  future::Future,
+  sync::{
+    Arc, RwLock,
+    atomic::{AtomicI32, Ordering},
+  },
+  time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
 use diesel::{
   PgConnection, QueryableByName, RunQueryDsl,
   r2d2::ConnectionManager,
   sql_query,
-  sql_types::{BigInt, Text},
+  sql_types::{Array, BigInt, Integer, Nullable, Text},
 };
 use r2d2::Pool;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::{task, time};
 
-use crate::{BlockStatus, ChainStatusType};
+use crate::{
+  BlockStatus, ChainStatusType, LEDGER_BALANCE_SCALE, LedgerAccount, VoteMemo, decode_memo,
+  memo::{MEMO_MAX_LENGTH, MEMO_USER_TAG, MEMO_VERSION_BYTE},
+};
+
+type ArchivePool = Pool<ConnectionManager<PgConnection>>;
 
 #[derive(Clone)]
-pub struct Archive(Pool<ConnectionManager<PgConnection>>);
+pub struct Archive {
+  pool: ArchivePool,
+  query_timeout: Duration,
+}
+
+/// The self-payments applied in non-orphaned blocks with a timestamp between
+/// `$1` and `$2`. Further conditions may be appended.
+const SELF_PAYMENTS_QUERY: &str = "SELECT DISTINCT pk.value as account, uc.memo as memo, uc.nonce as nonce, uc.hash as hash, b.height as height, b.chain_status as status, b.timestamp::bigint as timestamp
+  FROM user_commands AS uc
+  JOIN blocks_user_commands AS buc
+  ON uc.id = buc.user_command_id
+  JOIN blocks AS b
+  ON buc.block_id = b.id
+  JOIN public_keys AS pk
+  ON uc.source_id = pk.id
+  WHERE uc.command_type = 'payment'
+  AND uc.source_id = uc.receiver_id
+  AND NOT b.chain_status = 'orphaned'
+  AND buc.status = 'applied'
+  AND b.timestamp::bigint BETWEEN $1 AND $2";
 
 impl Archive {
-  pub fn new(archive_database_url: &String) -> Self {
+  pub fn new(archive_database_url: &String, query_timeout: Duration) -> Self {
     let archive_manager = ConnectionManager::<PgConnection>::new(archive_database_url);
     let pool = Pool::builder()
       .test_on_check_out(true)
       .build(archive_manager)
       .unwrap_or_else(|_| panic!("Error: failed to build `archive` connection pool"));
-    Self(pool)
+    Self { pool, query_timeout }
   }
 
-  pub fn fetch_chain_tip(&self) -> Result<i64> {
-    let connection = &mut self.0.get().context("failed to get archive db connection")?;
-    let result = sql_query("SELECT MAX(height) FROM blocks").get_result::<FetchChainTipResult>(connection)?;
+  pub async fn fetch_chain_tip(&self) -> Result<i64> {
+    let result = self
+      .run(|connection| sql_query("SELECT MAX(height) FROM blocks").get_result::<FetchChainTipResult>(connection))
+      .await?;
     Ok(result.max)
   }
 
-  pub fn fetch_latest_slot(&self) -> Result<i64> {
-    let connection = &mut self.0.get().context("failed to get archive db connection")?;
-    let result = sql_query("SELECT MAX(global_slot) FROM blocks").get_result::<FetchLatestSlotResult>(connection)?;
+  pub async fn fetch_latest_slot(&self) -> Result<i64> {
+    let result = self
+      .run(|connection| {
+        sql_query("SELECT MAX(global_slot) FROM blocks").get_result::<FetchLatestSlotResult>(connection)
+      })
+      .await?;
     Ok(result.max)
   }
 
-  pub fn fetch_transactions(&self, start_time: i64, end_time: i64) -> Result<Vec<FetchTransactionResult>> {
-    let connection = &mut self.0.get().context("failed to get archive db connection")?;
-    let results = sql_query(
-      "SELECT DISTINCT pk.value as account, uc.memo as memo, uc.nonce as nonce, uc.hash as hash, b.height as height, b.chain_status as status, b.timestamp::bigint as timestamp
-      FROM user_commands AS uc
-      JOIN blocks_user_commands AS buc
-      ON uc.id = buc.user_command_id
-      JOIN blocks AS b
-      ON buc.block_id = b.id
-      JOIN public_keys AS pk
-      ON uc.source_id = pk.id
-      WHERE uc.command_type = 'payment'
-      AND uc.source_id = uc.receiver_id
-      AND NOT b.chain_status = 'orphaned'
-      AND buc.status = 'applied'
-      AND b.timestamp::bigint BETWEEN $1 AND $2"
-    );
-    let results = results.bind::<BigInt, _>(start_time).bind::<BigInt, _>(end_time).get_results(connection)?;
-    Ok(results)
+  /// The timestamp of the block at `height`, or of the earliest one if there
+  /// are several that are not orphaned.
+  pub async fn fetch_block_timestamp(&self, height: i64) -> Result<Option<i64>> {
+    let result = self
+      .run(move |connection| {
+        sql_query(
+          "SELECT MIN(timestamp::bigint) AS timestamp FROM blocks WHERE height = $1 AND NOT chain_status = 'orphaned'",
+        )
+        .bind::<BigInt, _>(height)
+        .get_result::<FetchBlockTimestampResult>(connection)
+      })
+      .await?;
+    Ok(result.timestamp)
+  }
+
+  pub async fn fetch_transactions(
+    &self,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    self.fetch_transactions_above(0, start_time, end_time, memo_filter).await
+  }
+
+  /// The self-payments of `fetch_transactions` in blocks above `height`.
+  pub async fn fetch_transactions_above(
+    &self,
+    height: i64,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    let memo_ranges = memo_filter.ranges();
+    let memo_clause = if memo_ranges.is_none() {
+      ""
+    } else {
+      "AND EXISTS (SELECT 1 FROM unnest($4::text[], $5::text[]) AS r(lo, hi) WHERE uc.memo COLLATE \"C\" BETWEEN r.lo AND r.hi)"
+    };
+    let query = format!("{SELF_PAYMENTS_QUERY} AND b.height > $3 {memo_clause}");
+    let (memo_lo, memo_hi): (Vec<String>, Vec<String>) = memo_ranges.unwrap_or_default().into_iter().unzip();
+    self
+      .run(move |connection| {
+        let query =
+          sql_query(query).bind::<BigInt, _>(start_time).bind::<BigInt, _>(end_time).bind::<BigInt, _>(height);
+        if memo_clause.is_empty() {
+          query.get_results(connection)
+        } else {
+          query.bind::<Array<Text>, _>(memo_lo).bind::<Array<Text>, _>(memo_hi).get_results(connection)
+        }
+      })
+      .await
+      .map(|transactions: Vec<FetchTransactionResult>| {
+        transactions.into_iter().filter(|transaction| memo_filter.matches(&transaction.memo)).collect()
+      })
+  }
+
+  /// Every self-payment of `public_key` between `start_time` and `end_time`,
+  /// whatever its memo.
+  pub async fn fetch_account_transactions(
+    &self,
+    public_key: &str,
+    start_time: i64,
+    end_time: i64,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    let public_key = public_key.to_string();
+    self
+      .run(move |connection| {
+        sql_query(format!("{SELF_PAYMENTS_QUERY} AND pk.value = $3"))
+          .bind::<BigInt, _>(start_time)
+          .bind::<BigInt, _>(end_time)
+          .bind::<Text, _>(public_key)
+          .get_results(connection)
+      })
+      .await
+  }
+
+  /// The hash of the staking ledger used during `epoch`.
+  pub async fn fetch_staking_ledger_hash(&self, epoch: i64) -> Result<String> {
+    let result = self
+      .run(move |connection| {
+        sql_query(
+          "SELECT slh.value AS hash
+          FROM blocks AS b
+          JOIN epoch_data AS ed
+          ON b.staking_epoch_data_id = ed.id
+          JOIN snarked_ledger_hashes AS slh
+          ON ed.ledger_hash_id = slh.id
+          WHERE b.chain_status = 'canonical'
+          AND b.global_slot / $1 = $2
+          LIMIT 1",
+        )
+        .bind::<BigInt, _>(SLOTS_PER_EPOCH)
+        .bind::<BigInt, _>(epoch)
+        .get_results::<FetchLedgerHashResult>(connection)
+      })
+      .await?;
+    result.into_iter().next().map(|r| r.hash).ok_or_else(|| anyhow!("no canonical block in epoch {epoch}"))
+  }
+
+  /// Rebuilds the accounts of the staking ledger `ledger_hash` from the
+  /// balances and delegations recorded up to the first canonical block whose
+  /// snarked ledger it is.
+  ///
+  /// The result is an approximation of the ledger:
+  /// - the balances are those of the block's staged ledger, which may include
+  ///   transactions not yet in its snarked ledger;
+  /// - accounts of the genesis ledger which no command has touched since have
+  ///   no recorded balance, so they are missing;
+  /// - delegations made in the genesis ledger are not recorded as commands, so
+  ///   those accounts delegate to themselves.
+  pub async fn fetch_ledger_accounts(&self, ledger_hash: &str) -> Result<Vec<LedgerAccount>> {
+    let ledger_hash = ledger_hash.to_string();
+    let hash = ledger_hash.clone();
+    let height = self
+      .run(move |connection| {
+        sql_query(
+          "SELECT MIN(b.height) AS height
+          FROM blocks AS b
+          JOIN snarked_ledger_hashes AS slh
+          ON b.snarked_ledger_hash_id = slh.id
+          WHERE b.chain_status = 'canonical'
+          AND slh.value = $1",
+        )
+        .bind::<Text, _>(hash)
+        .get_result::<FetchLedgerHeightResult>(connection)
+      })
+      .await?
+      .height
+      .ok_or_else(|| anyhow!("no canonical block with snarked ledger {ledger_hash} in the archive"))?;
+
+    let balances = self
+      .run(move |connection| {
+        sql_query(
+          "SELECT DISTINCT ON (bal.public_key_id) pk.value AS pk, bal.balance AS balance
+          FROM balances AS bal
+          JOIN blocks AS b
+          ON bal.block_id = b.id
+          JOIN public_keys AS pk
+          ON bal.public_key_id = pk.id
+          WHERE b.chain_status = 'canonical'
+          AND bal.block_height <= $1
+          ORDER BY bal.public_key_id, bal.block_height DESC, bal.block_sequence_no DESC, bal.block_secondary_sequence_no DESC",
+        )
+        .bind::<BigInt, _>(height)
+        .get_results::<FetchBalanceResult>(connection)
+      })
+      .await?;
+
+    let delegations = self
+      .run(move |connection| {
+        sql_query(
+          "SELECT DISTINCT ON (uc.source_id) source.value AS pk, receiver.value AS delegate
+          FROM user_commands AS uc
+          JOIN blocks_user_commands AS buc
+          ON uc.id = buc.user_command_id
+          JOIN blocks AS b
+          ON buc.block_id = b.id
+          JOIN public_keys AS source
+          ON uc.source_id = source.id
+          JOIN public_keys AS receiver
+          ON uc.receiver_id = receiver.id
+          WHERE uc.command_type = 'delegation'
+          AND buc.status = 'applied'
+          AND b.chain_status = 'canonical'
+          AND b.height <= $1
+          ORDER BY uc.source_id, b.height DESC, buc.sequence_no DESC",
+        )
+        .bind::<BigInt, _>(height)
+        .get_results::<FetchDelegationResult>(connection)
+      })
+      .await?;
+
+    Ok(ledger_accounts(balances, delegations))
+  }
+
+  /// Runs a blocking Diesel query on the blocking thread pool.
+  ///
+  /// The query is cancelled on the Postgres side if it outlives the configured
+  /// timeout, or if the returned future is dropped before completion (e.g.
+  /// because the HTTP client disconnected).
+  async fn run<T, F>(&self, query: F) -> Result<T>
+  where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> diesel::QueryResult<T> + Send + 'static,
+  {
+    let mut guard = QueryGuard::new(self.pool.clone());
+    let pool = self.pool.clone();
+    let backend_pid = guard.backend_pid.clone();
+    let handle = task::spawn_blocking(move || -> Result<T> {
+      let mut connection = pool.get().context("failed to get archive db connection")?;
+      let pid = sql_query("SELECT pg_backend_pid() AS pid").get_result::<FetchBackendPidResult>(&mut connection)?.pid;
+      backend_pid.store(pid, Ordering::SeqCst);
+      let result = query(&mut connection);
+      // Forget the backend before the connection goes back to the pool, where
+      // it may serve another query that must not be cancelled.
+      backend_pid.store(0, Ordering::SeqCst);
+      drop(connection);
+      Ok(result?)
+    });
+    let result = time::timeout(self.query_timeout, handle)
+      .await
+      .map_err(|_| anyhow!("archive query timed out after {}s", self.query_timeout.as_secs()))?
+      .context("archive query task failed")?;
+    guard.complete();
+    result
+  }
+}
+
+/// Cancels the in-flight query of the connection it tracks when dropped
+/// before [`QueryGuard::complete`] is called. The tracked backend is cleared
+/// as soon as the query returns, before its connection is released.
+struct QueryGuard {
+  pool: ArchivePool,
+  backend_pid: Arc<AtomicI32>,
+  completed: bool,
+}
+
+impl QueryGuard {
+  fn new(pool: ArchivePool) -> Self {
+    Self { pool, backend_pid: Arc::new(AtomicI32::new(0)), completed: false }
+  }
+
+  fn complete(&mut self) {
+    self.completed = true;
   }
 }
 
+impl Drop for QueryGuard {
+  fn drop(&mut self) {
+    let pid = self.backend_pid.load(Ordering::SeqCst);
+    if self.completed || pid == 0 {
+      return;
+    }
+    let pool = self.pool.clone();
+    task::spawn_blocking(move || {
+      let cancelled = pool.get().map_err(anyhow::Error::from).and_then(|mut connection| {
+        Ok(sql_query("SELECT pg_cancel_backend($1)").bind::<Integer, _>(pid).execute(&mut connection)?)
+      });
+      match cancelled {
+        Ok(_) => tracing::info!("cancelled archive query on backend {pid}"),
+        Err(e) => tracing::warn!("failed to cancel archive query on backend {pid}: {e}"),
+      }
+    });
+  }
+}
+
+#[derive(QueryableByName)]
+struct FetchBackendPidResult {
+  #[diesel(sql_type = Integer)]
+  pid: i32,
+}
+
 #[derive(QueryableByName)]
 pub struct FetchChainTipResult {
   #[diesel(sql_type = BigInt)]
@@ -69,6 +340,63 @@ pub struct FetchLatestSlotResult {
 }
 
 #[derive(QueryableByName)]
+struct FetchBlockTimestampResult {
+  #[diesel(sql_type = Nullable<BigInt>)]
+  timestamp: Option<i64>,
+}
+
+#[derive(QueryableByName)]
+struct FetchLedgerHashResult {
+  #[diesel(sql_type = Text)]
+  hash: String,
+}
+
+#[derive(QueryableByName)]
+struct FetchLedgerHeightResult {
+  #[diesel(sql_type = Nullable<BigInt>)]
+  height: Option<i64>,
+}
+
+#[derive(QueryableByName)]
+pub struct FetchBalanceResult {
+  #[diesel(sql_type = Text)]
+  pub pk: String,
+  /// The balance in nanomina.
+  #[diesel(sql_type = BigInt)]
+  pub balance: i64,
+}
+
+#[derive(QueryableByName)]
+pub struct FetchDelegationResult {
+  #[diesel(sql_type = Text)]
+  pub pk: String,
+  #[diesel(sql_type = Text)]
+  pub delegate: String,
+}
+
+const SLOTS_PER_EPOCH: i64 = 7140;
+
+/// Combines the latest balance and delegation of each account into ledger
+/// accounts, sorted by public key. Accounts which never delegated delegate
+/// to themselves.
+pub fn ledger_accounts(
+  balances: Vec<FetchBalanceResult>,
+  delegations: Vec<FetchDelegationResult>,
+) -> Vec<LedgerAccount> {
+  let delegates: HashMap<String, String> = delegations.into_iter().map(|d| (d.pk, d.delegate)).collect();
+  let mut accounts: Vec<LedgerAccount> = balances
+    .into_iter()
+    .map(|b| {
+      let balance = Decimal::new(b.balance, LEDGER_BALANCE_SCALE).normalize().to_string();
+      let delegate = delegates.get(&b.pk).cloned().unwrap_or_else(|| b.pk.clone());
+      LedgerAccount::new(b.pk, balance, Some(delegate))
+    })
+    .collect();
+  accounts.sort_by(|a, b| a.pk.cmp(&b.pk));
+  accounts
+}
+
+#[derive(QueryableByName, Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct FetchTransactionResult {
   #[diesel(sql_type = Text)]
   pub account: String,
@@ -86,73 +414,527 @@ pub struct FetchTransactionResult {
   pub nonce: i64,
 }
 
-pub trait ArchiveInterface {
-  fn fetch_chain_tip(&self) -> Result<i64>;
-  fn fetch_latest_slot(&self) -> Result<i64>;
-  fn fetch_transactions(&self, start_time: i64, end_time: i64) -> Result<Vec<FetchTransactionResult>>;
+/// Restricts the transactions fetched from the archive by their memo.
+///
+/// Memos are stored base58check-encoded, and every user memo encodes to a
+/// string of the same length, so a memo text (or a prefix of one) corresponds
+/// to a range of encoded memos under byte-wise ordering. The filter is
+/// converted to such ranges and evaluated by the database, then checked
+/// against each fetched memo with [`MemoFilter::matches`]. The ranges only
+/// pin down a prefix of the memo, shortened when it has too many case
+/// variants to enumerate, so they may let through memos that the check
+/// rejects, but never exclude a matching one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoFilter {
+  /// Every memo.
+  Any,
+  /// Memos read as the same vote as one of the given strings, whatever their
+  /// case and spacing.
+  Exact(Vec<String>),
+  /// Memos whose text starts with the given string, ignoring ASCII case.
+  Prefix(String),
+}
+
+impl MemoFilter {
+  /// The inclusive ranges of encoded memos which include every memo accepted
+  /// by this filter, or `None` when every memo is accepted.
+  pub fn ranges(&self) -> Option<Vec<(String, String)>> {
+    match self {
+      MemoFilter::Any => None,
+      MemoFilter::Exact(texts) => Some(
+        texts
+          .iter()
+          .filter_map(|text| VoteMemo::parse(text))
+          .flat_map(|vote| {
+            // Any spacing of the vote is at least as long as its canonical
+            // text, and starts with the same first word.
+            let canonical = vote.to_string();
+            let first_word = canonical.split(' ').next().unwrap_or_default();
+            memo_ranges(first_word.as_bytes(), canonical.len())
+          })
+          .collect(),
+      ),
+      MemoFilter::Prefix(prefix) => Some(memo_ranges(prefix.as_bytes(), prefix.len())),
+    }
+  }
+
+  /// Whether the encoded `memo` is accepted by this filter.
+  pub fn matches(&self, memo: &str) -> bool {
+    match self {
+      MemoFilter::Any => true,
+      MemoFilter::Exact(texts) => {
+        let vote = decode_memo(memo).ok().and_then(|text| VoteMemo::parse(&text));
+        vote.is_some_and(|vote| texts.iter().any(|text| VoteMemo::parse(text).as_ref() == Some(&vote)))
+      }
+      MemoFilter::Prefix(prefix) => decode_memo(memo).is_ok_and(|text| {
+        text.len() >= prefix.len() && text.as_bytes()[.. prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+      }),
+    }
+  }
+}
+
+const MEMO_MAX_RANGES: usize = 1024;
+
+fn memo_in_ranges(ranges: Option<&[(String, String)]>, memo: &str) -> bool {
+  ranges.is_none_or(|ranges| ranges.iter().any(|(lo, hi)| lo.as_str() <= memo && memo <= hi.as_str()))
+}
+
+/// The ranges of encoded memos at least `min_length` bytes long whose text
+/// starts with `prefix`, in any ASCII case.
+fn memo_ranges(prefix: &[u8], min_length: usize) -> Vec<(String, String)> {
+  if min_length > MEMO_MAX_LENGTH {
+    return vec![];
+  }
+  let lengths = min_length ..= MEMO_MAX_LENGTH;
+
+  // Longest part of the prefix whose case variants can be enumerated, with a
+  // range for each variant and length.
+  let max_variants = MEMO_MAX_RANGES / lengths.clone().count();
+  let mut variants = 1;
+  let mut prefix_len = 0;
+  for byte in prefix {
+    let factor = if byte.is_ascii_alphabetic() { 2 } else { 1 };
+    if !byte.is_ascii() || variants * factor > max_variants {
+      break;
+    }
+    variants *= factor;
+    prefix_len += 1;
+  }
+
+  let mut ranges = Vec::new();
+  for variant in case_variants(&prefix[.. prefix_len]) {
+    for length in lengths.clone() {
+      let bound = |fill: u8| {
+        let mut bytes = vec![MEMO_VERSION_BYTE, MEMO_USER_TAG, length as u8];
+        bytes.extend_from_slice(&variant);
+        bytes.resize(MEMO_MAX_LENGTH + 3 + 4, fill);
+        bs58::encode(bytes).into_string()
+      };
+      ranges.push((bound(0x00), bound(0xff)));
+    }
+  }
+  ranges
+}
+
+fn case_variants(text: &[u8]) -> Vec<Vec<u8>> {
+  text.iter().fold(vec![vec![]], |variants, byte| {
+    let cases =
+      if byte.is_ascii_alphabetic() { vec![byte.to_ascii_lowercase(), byte.to_ascii_uppercase()] } else { vec![*byte] };
+    variants
+      .into_iter()
+      .flat_map(|variant| {
+        cases.iter().map(move |case| {
+          let mut variant = variant.clone();
+          variant.push(*case);
+          variant
+        })
+      })
+      .collect()
+  })
+}
+
+pub trait ArchiveInterface: Clone + Send + Sync + 'static {
+  fn fetch_chain_tip(&self) -> impl Future<Output = Result<i64>> + Send;
+  fn fetch_latest_slot(&self) -> impl Future<Output = Result<i64>> + Send;
+  fn fetch_block_timestamp(&self, height: i64) -> impl Future<Output = Result<Option<i64>>> + Send;
+  fn fetch_transactions(
+    &self,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+  ) -> impl Future<Output = Result<Vec<FetchTransactionResult>>> + Send;
+  /// The transactions of `fetch_transactions` in blocks above `height`.
+  fn fetch_transactions_above(
+    &self,
+    height: i64,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+  ) -> impl Future<Output = Result<Vec<FetchTransactionResult>>> + Send {
+    async move {
+      let transactions = self.fetch_transactions(start_time, end_time, memo_filter).await?;
+      Ok(transactions.into_iter().filter(|transaction| transaction.height > height).collect())
+    }
+  }
+  fn fetch_account_transactions(
+    &self,
+    public_key: &str,
+    start_time: i64,
+    end_time: i64,
+  ) -> impl Future<Output = Result<Vec<FetchTransactionResult>>> + Send;
 }
 
 impl ArchiveInterface for Archive {
-  fn fetch_chain_tip(&self) -> Result<i64> {
-    self.fetch_chain_tip()
+  async fn fetch_chain_tip(&self) -> Result<i64> {
+    self.fetch_chain_tip().await
   }
 
-  fn fetch_latest_slot(&self) -> Result<i64> {
-    self.fetch_latest_slot()
+  async fn fetch_latest_slot(&self) -> Result<i64> {
+    self.fetch_latest_slot().await
   }
 
-  fn fetch_transactions(&self, start_time: i64, end_time: i64) -> Result<Vec<FetchTransactionResult>> {
-    self.fetch_transactions(start_time, end_time)
+  async fn fetch_block_timestamp(&self, height: i64) -> Result<Option<i64>> {
+    self.fetch_block_timestamp(height).await
   }
+
+  async fn fetch_transactions(
+    &self,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    self.fetch_transactions(start_time, end_time, memo_filter).await
+  }
+
+  async fn fetch_transactions_above(
+    &self,
+    height: i64,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    self.fetch_transactions_above(height, start_time, end_time, memo_filter).await
+  }
+
+  async fn fetch_account_transactions(
+    &self,
+    public_key: &str,
+    start_time: i64,
+    end_time: i64,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    self.fetch_account_transactions(public_key, start_time, end_time).await
+  }
+}
+
+/// A block as seen by the archive's `blocks` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveBlock {
+  pub height: i64,
+  pub global_slot: i64,
+  pub timestamp: i64,
+  pub status: BlockStatus,
 }
 
-pub struct MockArchive;
+/// A user command included in a block, as seen by the archive's
+/// `user_commands` and `blocks_user_commands` tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveUserCommand {
+  pub block_height: i64,
+  pub kind: UserCommandKind,
+  pub source: String,
+  pub receiver: String,
+  pub hash: String,
+  pub memo: String,
+  pub nonce: i64,
+  pub applied: bool,
+}
 
-impl ArchiveInterface for MockArchive {
-  fn fetch_chain_tip(&self) -> Result<i64> {
-    Ok(100) // Return a mock value for the chain tip
+impl ArchiveUserCommand {
+  /// An applied self-payment, i.e. the shape of an on-chain vote.
+  pub fn vote(
+    block_height: i64,
+    account: impl Into<String>,
+    hash: impl Into<String>,
+    memo: impl Into<String>,
+    nonce: i64,
+  ) -> Self {
+    let account = account.into();
+    Self {
+      block_height,
+      kind: UserCommandKind::Payment,
+      source: account.clone(),
+      receiver: account,
+      hash: hash.into(),
+      memo: memo.into(),
+      nonce,
+      applied: true,
+    }
   }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserCommandKind {
+  Payment,
+  Delegation,
+}
+
+#[derive(Default)]
+struct MemoryArchiveState {
+  blocks: Vec<ArchiveBlock>,
+  user_commands: Vec<ArchiveUserCommand>,
+}
+
+/// An in-memory archive which answers queries like [`Archive`] does.
+///
+/// Clones share the same underlying state, so blocks and user commands can be
+/// seeded after the archive has been handed to an [`crate::Ocv`].
+#[derive(Clone, Default)]
+pub struct MemoryArchive(Arc<RwLock<MemoryArchiveState>>);
 
-  fn fetch_latest_slot(&self) -> Result<i64> {
-    Ok(200) // Return a mock value for the latest slot
+impl MemoryArchive {
+  pub fn new() -> Self {
+    Self::default()
   }
 
-  fn fetch_transactions(&self, start_time: i64, _end_time: i64) -> Result<Vec<FetchTransactionResult>> {
-    Ok(vec![FetchTransactionResult {
-      account: "mock_account".to_string(),
-      hash: "mock_hash".to_string(),
-      memo: "mock_memo".to_string(),
-      height: 1,
-      status: BlockStatus::Pending, // Use a mock value
-      timestamp: start_time + 1000,
-      nonce: 42,
-    }]) // Return a mock list of transactions
+  pub fn add_block(&self, block: ArchiveBlock) -> &Self {
+    self.0.write().expect("memory archive lock poisoned").blocks.push(block);
+    self
+  }
+
+  /// Updates the chain status of every block at `height`, e.g. to orphan it.
+  pub fn set_block_status(&self, height: i64, status: BlockStatus) -> &Self {
+    let mut state = self.0.write().expect("memory archive lock poisoned");
+    state.blocks.iter_mut().filter(|block| block.height == height).for_each(|block| block.status = status);
+    self
+  }
+
+  pub fn add_user_command(&self, user_command: ArchiveUserCommand) -> &Self {
+    self.0.write().expect("memory archive lock poisoned").user_commands.push(user_command);
+    self
+  }
+}
+
+impl ArchiveInterface for MemoryArchive {
+  async fn fetch_chain_tip(&self) -> Result<i64> {
+    let state = self.0.read().map_err(|_| anyhow!("memory archive lock poisoned"))?;
+    state.blocks.iter().map(|block| block.height).max().ok_or_else(|| anyhow!("memory archive has no blocks"))
+  }
+
+  async fn fetch_latest_slot(&self) -> Result<i64> {
+    let state = self.0.read().map_err(|_| anyhow!("memory archive lock poisoned"))?;
+    state.blocks.iter().map(|block| block.global_slot).max().ok_or_else(|| anyhow!("memory archive has no blocks"))
+  }
+
+  async fn fetch_block_timestamp(&self, height: i64) -> Result<Option<i64>> {
+    let state = self.0.read().map_err(|_| anyhow!("memory archive lock poisoned"))?;
+    Ok(
+      state
+        .blocks
+        .iter()
+        .filter(|block| block.height == height && block.status != BlockStatus::Orphaned)
+        .map(|block| block.timestamp)
+        .min(),
+    )
+  }
+
+  async fn fetch_transactions(
+    &self,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    let state = self.0.read().map_err(|_| anyhow!("memory archive lock poisoned"))?;
+    let memo_ranges = memo_filter.ranges();
+    let results = state
+      .user_commands
+      .iter()
+      .filter(|uc| uc.kind == UserCommandKind::Payment && uc.source == uc.receiver && uc.applied)
+      .filter(|uc| memo_in_ranges(memo_ranges.as_deref(), &uc.memo) && memo_filter.matches(&uc.memo))
+      .flat_map(|uc| {
+        state
+          .blocks
+          .iter()
+          .filter(move |b| b.height == uc.block_height)
+          .filter(|b| b.status != BlockStatus::Orphaned && (start_time ..= end_time).contains(&b.timestamp))
+          .map(move |b| FetchTransactionResult {
+            account: uc.source.clone(),
+            hash: uc.hash.clone(),
+            memo: uc.memo.clone(),
+            height: b.height,
+            status: b.status,
+            timestamp: b.timestamp,
+            nonce: uc.nonce,
+          })
+      })
+      .collect();
+    Ok(results)
+  }
+
+  async fn fetch_account_transactions(
+    &self,
+    public_key: &str,
+    start_time: i64,
+    end_time: i64,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    let transactions = self.fetch_transactions(start_time, end_time, &MemoFilter::Any).await?;
+    Ok(transactions.into_iter().filter(|t| t.account == public_key).collect())
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::encode_memo;
 
-  #[test]
-  fn test_fetch_chain_tip() {
-    let archive = MockArchive;
-    let chain_tip = archive.fetch_chain_tip().unwrap();
+  #[tokio::test]
+  async fn test_fetch_chain_tip() {
+    let archive = get_archive();
+    let chain_tip = archive.fetch_chain_tip().await.unwrap();
     assert_eq!(chain_tip, 100);
   }
 
-  #[test]
-  fn test_fetch_latest_slot() {
-    let archive = MockArchive;
-    let latest_slot = archive.fetch_latest_slot().unwrap();
+  #[tokio::test]
+  async fn test_fetch_latest_slot() {
+    let archive = get_archive();
+    let latest_slot = archive.fetch_latest_slot().await.unwrap();
     assert_eq!(latest_slot, 200);
   }
 
-  #[test]
-  fn test_fetch_transactions() {
-    let archive = MockArchive;
-    let transactions = archive.fetch_transactions(1733371364000, 1733803364000).unwrap();
+  #[tokio::test]
+  async fn test_fetch_transactions() {
+    let archive = get_archive();
+    let transactions = archive.fetch_transactions(1733371364000, 1733803364000, &MemoFilter::Any).await.unwrap();
     assert_eq!(transactions.len(), 1);
     assert_eq!(transactions[0].account, "mock_account");
+    assert_eq!(transactions[0].height, 1);
+    assert_eq!(transactions[0].timestamp, 1733371365000);
+  }
+
+  #[tokio::test]
+  async fn test_fetch_transactions_filters_like_archive() {
+    let archive = get_archive();
+    archive
+      .add_block(ArchiveBlock { height: 2, global_slot: 2, timestamp: 1733371366000, status: BlockStatus::Orphaned })
+      .add_user_command(ArchiveUserCommand::vote(2, "orphaned", "h2", "memo", 1))
+      .add_user_command(ArchiveUserCommand { applied: false, ..ArchiveUserCommand::vote(1, "failed", "h3", "memo", 1) })
+      .add_user_command(ArchiveUserCommand {
+        receiver: "someone_else".to_string(),
+        ..ArchiveUserCommand::vote(1, "transfer", "h4", "memo", 1)
+      })
+      .add_user_command(ArchiveUserCommand {
+        kind: UserCommandKind::Delegation,
+        ..ArchiveUserCommand::vote(1, "delegation", "h5", "memo", 1)
+      })
+      .add_user_command(ArchiveUserCommand::vote(100, "out_of_window", "h6", "memo", 1));
+
+    let transactions = archive.fetch_transactions(1733371364000, 1733803364000, &MemoFilter::Any).await.unwrap();
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0].account, "mock_account");
+  }
+
+  #[tokio::test]
+  async fn test_fetch_transactions_by_memo() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(1, "yes", "h2", CFTEST_2, 1))
+      .add_user_command(ArchiveUserCommand::vote(1, "no", "h3", NO_CFTEST_2, 1));
+
+    let filter = MemoFilter::Exact(vec!["cftest-2".to_string(), "no cftest-2".to_string()]);
+    let transactions = archive.fetch_transactions(1733371364000, 1733803364000, &filter).await.unwrap();
+    assert_eq!(transactions.len(), 2);
+
+    let filter = MemoFilter::Exact(vec!["CFTEST-2".to_string()]);
+    let transactions = archive.fetch_transactions(1733371364000, 1733803364000, &filter).await.unwrap();
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0].account, "yes");
+  }
+
+  #[tokio::test]
+  async fn test_fetch_transactions_by_spaced_memo() {
+    let archive = get_archive();
+    for (i, memo) in ["CFTest-2 ", "No   cftest-2", "  cftest-2", "cftest-20", "no cftest"].into_iter().enumerate() {
+      archive.add_user_command(ArchiveUserCommand::vote(1, memo, format!("h{i}"), encode_memo(memo).unwrap(), 1));
+    }
+
+    let filter = MemoFilter::Exact(vec!["cftest-2".to_string(), "no cftest-2".to_string()]);
+    let transactions = archive.fetch_transactions(1733371364000, 1733803364000, &filter).await.unwrap();
+    let accounts: Vec<&str> = transactions.iter().map(|t| t.account.as_str()).collect();
+    assert_eq!(accounts, vec!["CFTest-2 ", "No   cftest-2"]);
+
+    // The account path reads every memo, the archive's own mock one included.
+    let transactions = archive.fetch_transactions(1733371364000, 1733803364000, &MemoFilter::Any).await.unwrap();
+    assert_eq!(transactions.len(), 6);
+  }
+
+  #[test]
+  fn test_memo_filter_exact() {
+    let filter = MemoFilter::Exact(vec!["cftest-2".to_string()]);
+    assert!(filter.matches(CFTEST_2));
+    assert!(!filter.matches(NO_CFTEST_2));
+    assert!(!filter.matches(MEF1_YES_1));
+
+    assert!(MemoFilter::Exact(vec!["mef1 yes 1".to_string()]).matches(MEF1_YES_1));
+    assert!(!MemoFilter::Exact(vec!["mef1 yes 10".to_string()]).matches(MEF1_YES_1));
+    assert!(!MemoFilter::Exact(vec![]).matches(MEF1_YES_1));
+    assert!(!MemoFilter::Exact(vec!["x".repeat(33)]).matches(MEF1_YES_1));
+  }
+
+  #[test]
+  fn test_memo_filter_ranges_include_matches() {
+    let filter = MemoFilter::Exact(vec!["mef1 yes 1".to_string(), "no cftest-2".to_string()]);
+    let ranges = filter.ranges().unwrap();
+    for text in ["MEF1 YES 1", "mef1  yes\t1 ", "No cftest-2", "NO \t CFTEST-2  "] {
+      let memo = encode_memo(text).unwrap();
+      assert!(filter.matches(&memo), "{text}");
+      assert!(memo_in_ranges(Some(&ranges), &memo), "{text}");
+    }
+    for text in [" mef1 yes 1", "mef1 yes 10", "no cftest-20", "nocftest-2"] {
+      assert!(!filter.matches(&encode_memo(text).unwrap()), "{text}");
+    }
+  }
+
+  #[test]
+  fn test_memo_filter_exact_widens_long_keys() {
+    let filter = MemoFilter::Exact(vec!["minaexplorer gas fee service".to_string()]);
+    assert!(filter.ranges().unwrap().len() <= MEMO_MAX_RANGES);
+    assert!(filter.matches(GAS_FEE_SERVICE));
+    assert!(!filter.matches(CFTEST_2));
+  }
+
+  #[test]
+  fn test_memo_filter_prefix() {
+    let filter = MemoFilter::Prefix("mef".to_string());
+    assert!(filter.matches(MEF1_YES_1));
+    assert!(filter.matches(MEF_1_3_1_39));
+    assert!(!filter.matches(CFTEST_2));
+
+    let filter = MemoFilter::Prefix("mef ".to_string());
+    assert!(!filter.matches(MEF1_YES_1));
+    assert!(filter.matches(MEF_1_3_1_39));
+
+    assert!(MemoFilter::Any.matches(CFTEST_2));
+    assert!(MemoFilter::Any.ranges().is_none());
+  }
+
+  #[test]
+  fn test_ledger_accounts() {
+    let balance = |pk: &str, balance| FetchBalanceResult { pk: pk.to_string(), balance };
+    let delegation =
+      |pk: &str, delegate: &str| FetchDelegationResult { pk: pk.to_string(), delegate: delegate.to_string() };
+    let accounts =
+      ledger_accounts(vec![balance("B", 1_500_000_000), balance("A", 2_000_000_000), balance("C", 1)], vec![
+        delegation("B", "A"),
+        delegation("C", "C"),
+      ]);
+    assert_eq!(accounts, vec![
+      LedgerAccount::new("A".to_string(), "2".to_string(), Some("A".to_string())),
+      LedgerAccount::new("B".to_string(), "1.5".to_string(), Some("A".to_string())),
+      LedgerAccount::new("C".to_string(), "0.000000001".to_string(), Some("C".to_string())),
+    ]);
+
+    // Genesis accounts no command touched have no balance to rebuild them
+    // from, and genesis delegations have no command recording them.
+    let accounts = ledger_accounts(vec![balance("G", 1_000_000_000)], vec![delegation("H", "G")]);
+    assert_eq!(accounts, vec![LedgerAccount::new("G".to_string(), "1".to_string(), Some("G".to_string()))]);
+  }
+
+  #[tokio::test]
+  async fn test_empty_archive_has_no_chain_tip() {
+    assert!(MemoryArchive::new().fetch_chain_tip().await.is_err());
+  }
+
+  const CFTEST_2: &str = "E4YdLeukpqzqyBAxujeELx9SZWoUW9MhcUfnGHF9PhQmxTJcpmj7j";
+  const NO_CFTEST_2: &str = "E4YjFkHVUXbEAkQcUrAEcS1fqvbncnn9Tuz2Jtb1Uu79zY9UAJRpd";
+  const MEF1_YES_1: &str = "E4Yh4PzVLrCiugdoaASo5Ve6Do755ey6vGqkURC8z7qcADqMUcp9K";
+  const MEF_1_3_1_39: &str = "E4YkwtLx9t8gRCWWoc8cACHxKFSywt23uaGKTfmkwF1sNMh87FMEi";
+  const GAS_FEE_SERVICE: &str = "E4ZJ3rmurwsMFrSvLdSAGRqmXRjYeZt84Wws4dixfpN67Xj7SrRLu";
+
+  fn get_archive() -> MemoryArchive {
+    let archive = MemoryArchive::new();
+    archive
+      .add_block(ArchiveBlock { height: 1, global_slot: 1, timestamp: 1733371365000, status: BlockStatus::Pending })
+      .add_block(ArchiveBlock { height: 100, global_slot: 200, timestamp: 1733900000000, status: BlockStatus::Pending })
+      .add_user_command(ArchiveUserCommand::vote(1, "mock_account", "mock_hash", "mock_memo", 42));
+    archive
   }
 }
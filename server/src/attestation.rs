@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{FetchTransactionResult, Ledger, Proposal, ProposalVersion, ReleaseStage, VoteRules};
+
+/// What a result was computed from, so that anyone holding the same inputs
+/// can reproduce it and compare.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Attestation {
+  /// The version of this server.
+  pub software_version: String,
+  /// The manifest entry of the proposal, for results of manifest proposals.
+  pub proposal: Option<Proposal>,
+  pub ledger: Option<LedgerDigest>,
+  pub archive: ArchiveInputs,
+  pub rules: TallyRules,
+}
+
+impl Attestation {
+  pub fn new(
+    proposal: Option<Proposal>,
+    ledger: Option<LedgerDigest>,
+    archive: ArchiveInputs,
+    rules: TallyRules,
+  ) -> Self {
+    Attestation { software_version: env!("CARGO_PKG_VERSION").to_string(), proposal, ledger, archive, rules }
+  }
+}
+
+/// The staking ledger votes were weighted with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LedgerDigest {
+  pub hash: String,
+  /// SHA-256 of the ledger's accounts re-serialized as JSON, see
+  /// [`Ledger::accounts_digest`]. It is not the digest of the ledger file
+  /// that was downloaded, so it cannot be checked against that file's hash.
+  pub digest: String,
+  pub num_accounts: usize,
+}
+
+impl LedgerDigest {
+  pub fn new(hash: impl Into<String>, ledger: &Ledger) -> Self {
+    LedgerDigest {
+      hash: hash.into(),
+      digest: ledger.accounts_digest().to_string(),
+      num_accounts: ledger.accounts().len(),
+    }
+  }
+}
+
+/// The archive transactions a result was computed from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ArchiveInputs {
+  pub chain_tip: i64,
+  pub start_time: i64,
+  pub end_time: i64,
+  /// The lowest and highest block containing one of the transactions.
+  pub block_range: Option<(i64, i64)>,
+  pub transaction_count: usize,
+  /// SHA-256 of the transactions, ordered by timestamp, hash and height and
+  /// serialized as JSON. Only the fields fixed once a transaction is in a
+  /// block are covered, not the status of the block, which depends on when
+  /// the transaction was read.
+  pub transactions_digest: String,
+}
+
+impl ArchiveInputs {
+  pub fn new(chain_tip: i64, start_time: i64, end_time: i64, transactions: &[FetchTransactionResult]) -> Self {
+    let mut sorted: Vec<&FetchTransactionResult> = transactions.iter().collect();
+    sorted.sort_by(|a, b| (a.timestamp, &a.hash, a.height).cmp(&(b.timestamp, &b.hash, b.height)));
+    let block_range = sorted.iter().map(|t| t.height).fold(None, |range, height| match range {
+      None => Some((height, height)),
+      Some((lo, hi)) => Some((i64::min(lo, height), i64::max(hi, height))),
+    });
+    let digested: Vec<DigestedTransaction> = sorted.iter().map(|t| DigestedTransaction::from(*t)).collect();
+    let json = serde_json::to_vec(&digested).expect("transactions serialize to JSON");
+    ArchiveInputs {
+      chain_tip,
+      start_time,
+      end_time,
+      block_range,
+      transaction_count: sorted.len(),
+      transactions_digest: sha256_hex(&json),
+    }
+  }
+}
+
+/// The fields of a transaction covered by
+/// [`ArchiveInputs::transactions_digest`].
+#[derive(Serialize)]
+struct DigestedTransaction<'a> {
+  hash: &'a str,
+  account: &'a str,
+  memo: &'a str,
+  height: i64,
+  nonce: i64,
+  timestamp: i64,
+}
+
+impl<'a> From<&'a FetchTransactionResult> for DigestedTransaction<'a> {
+  fn from(transaction: &'a FetchTransactionResult) -> Self {
+    DigestedTransaction {
+      hash: &transaction.hash,
+      account: &transaction.account,
+      memo: &transaction.memo,
+      height: transaction.height,
+      nonce: transaction.nonce,
+      timestamp: transaction.timestamp,
+    }
+  }
+}
+
+/// The settings a result was tallied under.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TallyRules {
+  Proposal { version: ProposalVersion, finality_depth: i64 },
+  Consideration { release_stage: ReleaseStage, min_positive_votes: usize, finality_depth: i64 },
+  RankedVote { vote_rules: VoteRules, finality_depth: i64 },
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+  format!("{:x}", Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::BlockStatus;
+
+  fn transaction(hash: &str, height: i64, timestamp: i64) -> FetchTransactionResult {
+    FetchTransactionResult {
+      account: "A".to_string(),
+      hash: hash.to_string(),
+      memo: "E4YdLeukpqzqyBAxujeELx9SZWoUW9MhcUfnGHF9PhQmxTJcpmj7j".to_string(),
+      height,
+      status: BlockStatus::Canonical,
+      timestamp,
+      nonce: 0,
+    }
+  }
+
+  #[test]
+  fn test_archive_inputs_ignore_order() {
+    let transactions = vec![transaction("1", 5, 5000), transaction("2", 2, 2000), transaction("3", 9, 9000)];
+    let inputs = ArchiveInputs::new(20, 0, 10_000, &transactions);
+    assert_eq!(inputs.block_range, Some((2, 9)));
+    assert_eq!(inputs.transaction_count, 3);
+
+    let reversed: Vec<_> = transactions.iter().rev().cloned().collect();
+    assert_eq!(ArchiveInputs::new(20, 0, 10_000, &reversed), inputs);
+
+    let changed = vec![transaction("1", 5, 5000), transaction("2", 2, 2000), transaction("4", 9, 9000)];
+    assert_ne!(ArchiveInputs::new(20, 0, 10_000, &changed).transactions_digest, inputs.transactions_digest);
+
+    // Blocks becoming canonical after the transactions were read do not
+    // change what was read.
+    let pending: Vec<_> =
+      transactions.iter().cloned().map(|t| FetchTransactionResult { status: BlockStatus::Pending, ..t }).collect();
+    assert_eq!(ArchiveInputs::new(20, 0, 10_000, &pending), inputs);
+
+    let empty = ArchiveInputs::new(20, 0, 10_000, &[]);
+    assert_eq!(empty.block_range, None);
+    assert_eq!(empty.transactions_digest, sha256_hex(b"[]"));
+  }
+
+  #[test]
+  fn test_sha256_hex() {
+    assert_eq!(sha256_hex(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+  }
+}
@@ -1,8 +1,57 @@
 use anyhow::Result;
-use clap::Parser;
-use mina_ocv::ServeArgs;
+use clap::{Parser, Subcommand};
+use mina_ocv::{
+  EncodeMemoArgs, ExportLedgerArgs, FetchLedgerArgs, MefConsiderationArgs, MefRankedArgs, ProposalsCommand, ReplayArgs,
+  ServeArgs, TallyArgs, VotePaymentArgs,
+};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Serve the API.
+  Serve(Box<ServeArgs>),
+  /// Print the result of a proposal.
+  Tally(Box<TallyArgs>),
+  /// Print the result of a proposal from a recorded bundle, offline. MEF
+  /// considerations and ranked votes cannot be recorded.
+  Replay(ReplayArgs),
+  /// Print the consideration votes of a MEF proposal.
+  MefConsideration(Box<MefConsiderationArgs>),
+  /// Print the ranked-choice result of a MEF round.
+  MefRanked(Box<MefRankedArgs>),
+  /// Download and verify a staking ledger.
+  FetchLedger(Box<FetchLedgerArgs>),
+  /// Build a staking ledger from the archive database.
+  ExportLedger(ExportLedgerArgs),
+  /// Print the base58 memo casting a vote.
+  EncodeMemo(EncodeMemoArgs),
+  /// Print an unsigned self-payment casting a vote.
+  VotePayment(VotePaymentArgs),
+  /// Work with proposals manifests.
+  Proposals {
+    #[command(subcommand)]
+    command: ProposalsCommand,
+  },
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-  ServeArgs::parse().serve().await
+  match Cli::parse().command {
+    Command::Serve(args) => args.serve().await,
+    Command::Tally(args) => args.tally().await,
+    Command::Replay(args) => args.replay().await,
+    Command::MefConsideration(args) => args.run().await,
+    Command::MefRanked(args) => args.run().await,
+    Command::FetchLedger(args) => args.fetch().await,
+    Command::ExportLedger(args) => args.export().await,
+    Command::EncodeMemo(args) => args.run(),
+    Command::VotePayment(args) => args.run(),
+    Command::Proposals { command } => command.run(),
+  }
 }
@@ -0,0 +1,289 @@
+use std::{
+  fs,
+  path::PathBuf,
+  sync::{Arc, Mutex},
+};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  ArchiveInterface, Caches, FetchTransactionResult, GetMinaProposalResultResponse, Ledger, LedgerSource, MemoFilter,
+  Ocv, Proposal, ReleaseStage,
+};
+
+/// Everything a proposal result is computed from, so that it can be tallied
+/// again without access to the archive or the ledger bucket.
+///
+/// Only the binary proposals of the manifest are covered: MEF considerations
+/// and ranked votes are computed from other queries and cannot be bundled.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TallyBundle {
+  pub proposal: Proposal,
+  pub release_stage: ReleaseStage,
+  pub finality_depth: i64,
+  pub chain_tip: i64,
+  pub latest_slot: i64,
+  pub transactions: Vec<FetchTransactionResult>,
+  pub ledger: Option<Ledger>,
+}
+
+impl TallyBundle {
+  /// Tallies proposal `id` against the archive of `ocv`, recording what was
+  /// read along the way. The result is recomputed even if a final one is
+  /// stored, so that the archive is always queried.
+  pub async fn record<A: ArchiveInterface>(
+    ocv: &Ocv<A>,
+    id: usize,
+  ) -> Result<(TallyBundle, GetMinaProposalResultResponse)> {
+    let recording = RecordingArchive { inner: ocv.archive.clone(), recorded: Arc::default() };
+    let recorder = ocv.with_archive(recording.clone());
+
+    let result = recorder.recompute_proposal_result(id).await?;
+    let ledger = match &result.proposal.ledger_hash {
+      Some(hash) => recorder.caches.ledger.get(hash).await.map(|ledger| ledger.as_ref().clone()),
+      None => None,
+    };
+    let recorded = recording.recorded.lock().map_err(|_| anyhow!("recording poisoned"))?.clone();
+    let bundle = TallyBundle {
+      proposal: result.proposal.clone(),
+      release_stage: ocv.release_stage,
+      finality_depth: ocv.finality_depth,
+      chain_tip: recorded.chain_tip.unwrap_or_default(),
+      latest_slot: recorded.latest_slot.unwrap_or_default(),
+      transactions: recorded.transactions,
+      ledger,
+    };
+    Ok((bundle, result))
+  }
+
+  /// Tallies the recorded proposal from the bundle alone.
+  pub async fn replay(&self) -> Result<GetMinaProposalResultResponse> {
+    let scratch = ScratchDir::new("replay")?;
+    let ocv = Ocv {
+      caches: Caches::build(),
+      archive: BundleArchive(Arc::new(self.clone())),
+      network: self.proposal.network,
+      release_stage: self.release_stage,
+      ledger_storage_path: scratch.0.clone(),
+      results_storage_path: scratch.0.clone(),
+      ledger_source: LedgerSource::Directory(scratch.0.clone()),
+      proposals: vec![self.proposal.clone()],
+      finality_depth: self.finality_depth,
+      require_ledger_manifests: false,
+      rounds: Vec::new(),
+    };
+    if let (Some(hash), Some(ledger)) = (&self.proposal.ledger_hash, &self.ledger) {
+      ocv.caches.ledger.insert(hash.clone(), Arc::new(ledger.clone())).await;
+    }
+    ocv.recompute_proposal_result(self.proposal.id).await
+  }
+}
+
+#[derive(Default, Clone)]
+struct Recorded {
+  chain_tip: Option<i64>,
+  latest_slot: Option<i64>,
+  transactions: Vec<FetchTransactionResult>,
+}
+
+/// Passes queries through to another archive, keeping the first chain tip
+/// and slot it returned and every transaction row.
+#[derive(Clone)]
+struct RecordingArchive<A> {
+  inner: A,
+  recorded: Arc<Mutex<Recorded>>,
+}
+
+impl<A> RecordingArchive<A> {
+  fn record(&self, update: impl FnOnce(&mut Recorded)) -> Result<()> {
+    update(&mut *self.recorded.lock().map_err(|_| anyhow!("recording poisoned"))?);
+    Ok(())
+  }
+}
+
+impl<A: ArchiveInterface> ArchiveInterface for RecordingArchive<A> {
+  async fn fetch_chain_tip(&self) -> Result<i64> {
+    let chain_tip = self.inner.fetch_chain_tip().await?;
+    self.record(|recorded| {
+      recorded.chain_tip.get_or_insert(chain_tip);
+    })?;
+    Ok(chain_tip)
+  }
+
+  async fn fetch_latest_slot(&self) -> Result<i64> {
+    let latest_slot = self.inner.fetch_latest_slot().await?;
+    self.record(|recorded| {
+      recorded.latest_slot.get_or_insert(latest_slot);
+    })?;
+    Ok(latest_slot)
+  }
+
+  async fn fetch_block_timestamp(&self, height: i64) -> Result<Option<i64>> {
+    self.inner.fetch_block_timestamp(height).await
+  }
+
+  async fn fetch_transactions(
+    &self,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    let transactions = self.inner.fetch_transactions(start_time, end_time, memo_filter).await?;
+    self.record(|recorded| {
+      for transaction in &transactions {
+        if !recorded.transactions.contains(transaction) {
+          recorded.transactions.push(transaction.clone());
+        }
+      }
+    })?;
+    Ok(transactions)
+  }
+
+  async fn fetch_account_transactions(
+    &self,
+    public_key: &str,
+    start_time: i64,
+    end_time: i64,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    self.inner.fetch_account_transactions(public_key, start_time, end_time).await
+  }
+}
+
+/// Answers queries from a recorded bundle, applying the same time and memo
+/// filters as the archive.
+#[derive(Clone)]
+struct BundleArchive(Arc<TallyBundle>);
+
+impl ArchiveInterface for BundleArchive {
+  async fn fetch_chain_tip(&self) -> Result<i64> {
+    Ok(self.0.chain_tip)
+  }
+
+  async fn fetch_latest_slot(&self) -> Result<i64> {
+    Ok(self.0.latest_slot)
+  }
+
+  /// Blocks are not recorded, and replays never store their result.
+  async fn fetch_block_timestamp(&self, _height: i64) -> Result<Option<i64>> {
+    Ok(None)
+  }
+
+  async fn fetch_transactions(
+    &self,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    Ok(
+      self
+        .0
+        .transactions
+        .iter()
+        .filter(|t| (start_time ..= end_time).contains(&t.timestamp) && memo_filter.matches(&t.memo))
+        .cloned()
+        .collect(),
+    )
+  }
+
+  async fn fetch_account_transactions(
+    &self,
+    public_key: &str,
+    start_time: i64,
+    end_time: i64,
+  ) -> Result<Vec<FetchTransactionResult>> {
+    let transactions = self.fetch_transactions(start_time, end_time, &MemoFilter::Any).await?;
+    Ok(transactions.into_iter().filter(|t| t.account == public_key).collect())
+  }
+}
+
+/// A temporary directory, removed when dropped.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+  fn new(purpose: &str) -> Result<Self> {
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_nanos();
+    let dir = std::env::temp_dir().join(format!("mina-ocv-{purpose}-{}-{nanos}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    Ok(ScratchDir(dir))
+  }
+}
+
+impl Drop for ScratchDir {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.0);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use rust_decimal::Decimal;
+
+  use super::*;
+  use crate::{
+    ArchiveBlock, ArchiveUserCommand, BlockStatus, LedgerAccount, MemoryArchive, Network, ProposalCategory,
+    ProposalVersion,
+  };
+
+  const YES: &str = "E4YdLeukpqzqyBAxujeELx9SZWoUW9MhcUfnGHF9PhQmxTJcpmj7j"; // cftest-2
+  const NO: &str = "E4YjFkHVUXbEAkQcUrAEcS1fqvbncnn9Tuz2Jtb1Uu79zY9UAJRpd"; // no cftest-2
+  const OTHER: &str = "E4Yf92G48v8FApR4EWQq3iKb2vZkHHxZHPaZ73NQNBXmHeXNzHHSp"; // Payment#0
+
+  #[tokio::test]
+  async fn test_replay_matches_live_tally() {
+    let archive = MemoryArchive::new();
+    for height in 1 ..= 20 {
+      archive.add_block(ArchiveBlock {
+        height,
+        global_slot: height,
+        timestamp: height * 1000,
+        status: BlockStatus::Pending,
+      });
+    }
+    archive
+      .add_user_command(ArchiveUserCommand::vote(2, "A", "1", YES, 0))
+      .add_user_command(ArchiveUserCommand::vote(3, "B", "2", NO, 0))
+      .add_user_command(ArchiveUserCommand::vote(3, "C", "3", YES, 0))
+      .add_user_command(ArchiveUserCommand::vote(4, "E", "4", OTHER, 0));
+    let scratch = ScratchDir::new("test").unwrap();
+    let ocv = Ocv {
+      caches: Caches::build(),
+      archive,
+      network: Network::Devnet,
+      release_stage: ReleaseStage::Development,
+      ledger_storage_path: scratch.0.clone(),
+      results_storage_path: scratch.0.clone(),
+      ledger_source: LedgerSource::Directory(scratch.0.clone()),
+      proposals: vec![Proposal {
+        id: 1,
+        key: "cftest-2".to_string(),
+        start_time: 0,
+        end_time: 100_000,
+        epoch: 1,
+        ledger_hash: Some("ledger".to_string()),
+        category: ProposalCategory::Core,
+        version: ProposalVersion::V2,
+        title: "Title".to_string(),
+        description: String::new(),
+        url: String::new(),
+        network: Network::Devnet,
+      }],
+      finality_depth: 10,
+      require_ledger_manifests: false,
+      rounds: Vec::new(),
+    };
+    let accounts = ["A", "B", "C", "E"].map(|pk| LedgerAccount::new(pk.to_string(), "1".to_string(), None));
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(Ledger::new(accounts.to_vec()))).await;
+
+    let (bundle, live) = TallyBundle::record(&ocv, 1).await.unwrap();
+    assert_eq!(bundle.chain_tip, 20);
+    assert_eq!(bundle.transactions.len(), 3);
+    assert_eq!(bundle.ledger.as_ref().map(|ledger| ledger.accounts().len()), Some(4));
+
+    let bundle: TallyBundle = serde_json::from_str(&serde_json::to_string(&bundle).unwrap()).unwrap();
+    let replayed = bundle.replay().await.unwrap();
+    assert_eq!(serde_json::to_string(&replayed).unwrap(), serde_json::to_string(&live).unwrap());
+    assert_eq!(replayed.positive_stake_weight, Decimal::new(2, 0));
+    assert_eq!(replayed.negative_stake_weight, Decimal::new(1, 0));
+  }
+}
@@ -0,0 +1,449 @@
+use std::{fmt::Write, fs, path::PathBuf};
+
+use anyhow::{Result, anyhow, ensure};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::{
+  GetMinaProposalResultResponse, Ledger, LedgerConfig, OcvConfig, ProposalsManifest, TallyBundle, VoteMemo, VotePayment,
+};
+
+#[derive(Clone, Copy, ValueEnum, Debug, PartialEq)]
+pub enum OutputFormat {
+  Json,
+  Table,
+}
+
+/// Prints the weighted result of a proposal.
+#[derive(Clone, Parser)]
+pub struct TallyArgs {
+  /// The id of the proposal.
+  pub proposal_id: usize,
+  /// How the result is printed.
+  #[clap(long, value_enum, default_value_t = OutputFormat::Json)]
+  pub format: OutputFormat,
+  /// Write the inputs of the tally to this file, to be replayed offline.
+  /// Only proposal results can be recorded, not MEF considerations or ranked
+  /// votes.
+  #[clap(long)]
+  pub record_bundle: Option<PathBuf>,
+  /// Recompute the result even if a final one is stored, and store nothing.
+  #[clap(long)]
+  pub no_store: bool,
+  /// OCV Args.
+  #[command(flatten)]
+  pub config: OcvConfig,
+}
+
+impl TallyArgs {
+  pub async fn tally(&self) -> Result<()> {
+    let ocv = self.config.to_ocv().await?;
+    let result = match &self.record_bundle {
+      Some(path) => {
+        let (bundle, result) = TallyBundle::record(&ocv, self.proposal_id).await?;
+        fs::write(path, serde_json::to_vec(&bundle)?)?;
+        result
+      }
+      None if self.no_store => ocv.recompute_proposal_result(self.proposal_id).await?,
+      None => ocv.proposal_result(self.proposal_id).await?,
+    };
+    print!("{}", render(&result, self.format)?);
+    Ok(())
+  }
+}
+
+/// Tallies a proposal from a bundle recorded with `tally --record-bundle`,
+/// without access to the archive or the ledger bucket. MEF considerations
+/// and ranked votes cannot be replayed.
+#[derive(Clone, Parser)]
+pub struct ReplayArgs {
+  /// The recorded bundle.
+  pub bundle: PathBuf,
+  /// How the result is printed.
+  #[clap(long, value_enum, default_value_t = OutputFormat::Json)]
+  pub format: OutputFormat,
+}
+
+impl ReplayArgs {
+  pub async fn replay(&self) -> Result<()> {
+    let bundle: TallyBundle = serde_json::from_slice(&fs::read(&self.bundle)?)?;
+    let result = bundle.replay().await?;
+    print!("{}", render(&result, self.format)?);
+    Ok(())
+  }
+}
+
+/// Prints the consideration votes of a MEF proposal, for its round in the
+/// manifest or, given a window, as a what-if.
+#[derive(Clone, Parser)]
+pub struct MefConsiderationArgs {
+  pub round_id: usize,
+  pub proposal_id: usize,
+  /// Start of the voting window, in milliseconds since the epoch.
+  #[clap(requires = "end_time")]
+  pub start_time: Option<i64>,
+  /// End of the voting window, in milliseconds since the epoch.
+  pub end_time: Option<i64>,
+  /// The ledger to weight the votes with.
+  #[clap(long, requires = "start_time")]
+  pub ledger_hash: Option<String>,
+  /// OCV Args.
+  #[command(flatten)]
+  pub config: OcvConfig,
+}
+
+impl MefConsiderationArgs {
+  pub async fn run(&self) -> Result<()> {
+    let ocv = self.config.to_ocv().await?;
+    let result = match (self.start_time, self.end_time) {
+      (Some(start_time), Some(end_time)) => {
+        ocv
+          .proposal_consideration(self.round_id, self.proposal_id, start_time, end_time, self.ledger_hash.clone())
+          .await?
+      }
+      _ => ocv.round_consideration(self.round_id, self.proposal_id).await?,
+    };
+    print!("{}", to_json(&result)?);
+    Ok(())
+  }
+}
+
+/// Prints the ranked-choice result of a MEF round, from the manifest or,
+/// given a window, as a what-if.
+#[derive(Clone, Parser)]
+pub struct MefRankedArgs {
+  pub round_id: usize,
+  /// Start of the voting window, in milliseconds since the epoch.
+  #[clap(requires = "end_time")]
+  pub start_time: Option<i64>,
+  /// End of the voting window, in milliseconds since the epoch.
+  pub end_time: Option<i64>,
+  /// The ledger to weight the votes with.
+  #[clap(long, requires = "start_time")]
+  pub ledger_hash: Option<String>,
+  /// OCV Args.
+  #[command(flatten)]
+  pub config: OcvConfig,
+}
+
+impl MefRankedArgs {
+  pub async fn run(&self) -> Result<()> {
+    let ocv = self.config.to_ocv().await?;
+    let result = match (self.start_time, self.end_time) {
+      (Some(start_time), Some(end_time)) => {
+        ocv.run_ranked_vote(self.round_id, start_time, end_time, self.ledger_hash.clone()).await?
+      }
+      _ => ocv.round_ranked_vote(self.round_id).await?,
+    };
+    print!("{}", to_json(&result)?);
+    Ok(())
+  }
+}
+
+/// Downloads and verifies a staking ledger into `ledger_storage_path`.
+#[derive(Clone, Parser)]
+pub struct FetchLedgerArgs {
+  /// The hash of the ledger.
+  pub hash: String,
+  /// Staking ledger Args.
+  #[command(flatten)]
+  pub ledger: LedgerConfig,
+}
+
+impl FetchLedgerArgs {
+  pub async fn fetch(&self) -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let storage_path = PathBuf::from(&self.ledger.ledger_storage_path);
+    fs::create_dir_all(&storage_path)?;
+    let ledger =
+      Ledger::fetch_with(&self.ledger.source()?, &storage_path, self.ledger.require_ledger_manifests, &self.hash)
+        .await?;
+    println!(
+      "{}: {} accounts, {} total currency",
+      storage_path.join(format!("{}.json", self.hash)).display(),
+      ledger.accounts().len(),
+      ledger.total_currency()
+    );
+    Ok(())
+  }
+}
+
+/// A vote, given as `--proposal KEY [--no]` for a proposal,
+/// `--mef-round R --proposal P --yes|--no` for the consideration of a MEF
+/// proposal, or `--mef-round R --rank P...` for the ranking of a MEF round.
+#[derive(Clone, Parser)]
+#[clap(group(ArgGroup::new("choice").required(true).args(["proposal", "rank"])))]
+pub struct VoteArgs {
+  /// The key of the proposal voted on or, with `--mef-round`, the id of the
+  /// MEF proposal voted into consideration.
+  #[clap(long)]
+  pub proposal: Option<String>,
+  /// Vote for the MEF proposal.
+  #[clap(long, requires = "mef_round", conflicts_with = "no")]
+  pub yes: bool,
+  /// Vote against the proposal.
+  #[clap(long, conflicts_with = "rank")]
+  pub no: bool,
+  /// The MEF round voted in.
+  #[clap(long)]
+  pub mef_round: Option<usize>,
+  /// The ids of the candidate proposals of the MEF round, most preferred
+  /// first.
+  #[clap(long, num_args = 1 .., requires = "mef_round")]
+  pub rank: Vec<usize>,
+}
+
+impl VoteArgs {
+  pub fn memo(&self) -> Result<VoteMemo> {
+    match (self.mef_round, &self.proposal) {
+      (None, Some(key)) => {
+        let text = if self.no { format!("no {key}") } else { key.clone() };
+        VoteMemo::parse(&text)
+          .filter(|memo| memo.is_binary_vote_on(key))
+          .ok_or_else(|| anyhow!("{key:?} is not read as a vote on a proposal key"))
+      }
+      (Some(round_id), Some(proposal_id)) => {
+        ensure!(self.yes || self.no, "--yes or --no is required to vote on a MEF proposal");
+        let proposal_id =
+          proposal_id.parse().map_err(|_| anyhow!("MEF proposal id {proposal_id:?} is not a number"))?;
+        Ok(VoteMemo::MefConsideration { round_id, proposal_id, yes: self.yes })
+      }
+      (Some(round_id), None) => {
+        Ok(VoteMemo::MefRanked { round_id, choices: self.rank.iter().map(usize::to_string).collect() })
+      }
+      (None, None) => unreachable!("clap requires --mef-round with --rank"),
+    }
+  }
+}
+
+/// Prints the base58 memo casting a vote.
+#[derive(Clone, Parser)]
+pub struct EncodeMemoArgs {
+  #[command(flatten)]
+  pub vote: VoteArgs,
+}
+
+impl EncodeMemoArgs {
+  pub fn run(&self) -> Result<()> {
+    println!("{}", self.vote.memo()?.encode()?);
+    Ok(())
+  }
+}
+
+/// Prints an unsigned self-payment casting a vote, as JSON for a wallet or
+/// signer to sign and send.
+#[derive(Clone, Parser)]
+pub struct VotePaymentArgs {
+  #[command(flatten)]
+  pub vote: VoteArgs,
+  /// The public key of the voter.
+  #[clap(long)]
+  pub sender: String,
+  /// The fee, in nanomina.
+  #[clap(long)]
+  pub fee: u64,
+  /// The nonce of the voter's account.
+  #[clap(long)]
+  pub nonce: u32,
+  /// The last global slot the payment can be included in.
+  #[clap(long)]
+  pub valid_until: Option<u32>,
+}
+
+impl VotePaymentArgs {
+  pub fn run(&self) -> Result<()> {
+    let payment = VotePayment::new(&self.sender, &self.vote.memo()?, self.fee, self.nonce, self.valid_until)?;
+    print!("{}", to_json(&payment)?);
+    Ok(())
+  }
+}
+
+#[derive(Clone, Subcommand)]
+pub enum ProposalsCommand {
+  /// Check a proposals manifest for mistakes.
+  Validate {
+    /// The manifest to check.
+    file: PathBuf,
+  },
+  /// Print the id, key, network and voting window of each proposal.
+  List {
+    /// The manifest to list, by default the one built into the server.
+    file: Option<PathBuf>,
+  },
+}
+
+impl ProposalsCommand {
+  pub fn run(&self) -> Result<()> {
+    match self {
+      ProposalsCommand::Validate { file } => {
+        let manifest: ProposalsManifest = serde_json::from_slice(&fs::read(file)?)?;
+        let problems = manifest.validate();
+        if !problems.is_empty() {
+          return Err(anyhow!("{} is invalid:\n{}", file.display(), problems.join("\n")));
+        }
+        println!("{}: {} proposals, no problems found", file.display(), manifest.proposals.len());
+        Ok(())
+      }
+      ProposalsCommand::List { file } => {
+        let manifest: ProposalsManifest = match file {
+          Some(file) => serde_json::from_slice(&fs::read(file)?)?,
+          None => serde_json::from_slice(include_bytes!("../proposals/proposals.json"))?,
+        };
+        print!("{}", to_proposals_table(&manifest));
+        Ok(())
+      }
+    }
+  }
+}
+
+fn render(result: &GetMinaProposalResultResponse, format: OutputFormat) -> Result<String> {
+  match format {
+    OutputFormat::Json => to_json(result),
+    OutputFormat::Table => Ok(to_table(result)),
+  }
+}
+
+fn to_json(value: &impl Serialize) -> Result<String> {
+  Ok(serde_json::to_string_pretty(value)? + "\n")
+}
+
+fn to_table(result: &GetMinaProposalResultResponse) -> String {
+  let mut table = String::new();
+  let proposal = &result.proposal;
+  let _ = writeln!(table, "Proposal {} ({}): {}", proposal.id, proposal.key, proposal.title);
+  let _ = writeln!(table, "Total stake:    {}", result.total_stake_weight);
+  let _ = writeln!(table, "Positive stake: {}", result.positive_stake_weight);
+  let _ = writeln!(table, "Negative stake: {}", result.negative_stake_weight);
+  let _ = writeln!(table);
+  let _ = writeln!(table, "{:<55} {:>24} {:<24} {:>8} {:<9}", "ACCOUNT", "WEIGHT", "MEMO", "HEIGHT", "STATUS");
+  for vote in &result.votes {
+    let _ = writeln!(
+      table,
+      "{:<55} {:>24} {:<24} {:>8} {:<9}",
+      vote.account,
+      vote.weight,
+      vote.memo,
+      vote.height,
+      format!("{:?}", vote.status)
+    );
+  }
+  table
+}
+
+/// The voting window is in milliseconds since the epoch, as in the manifest.
+fn to_proposals_table(manifest: &ProposalsManifest) -> String {
+  let mut table = String::new();
+  let _ = writeln!(table, "{:>4} {:<24} {:<8} {:>14} {:>14}", "ID", "KEY", "NETWORK", "START", "END");
+  for proposal in &manifest.proposals {
+    let _ = writeln!(
+      table,
+      "{:>4} {:<24} {:<8} {:>14} {:>14}",
+      proposal.id,
+      proposal.key,
+      proposal.network.to_string(),
+      proposal.start_time,
+      proposal.end_time
+    );
+  }
+  table
+}
+
+#[cfg(test)]
+mod tests {
+  use rust_decimal::Decimal;
+
+  use super::*;
+  use crate::{BlockStatus, Network, Proposal, ProposalCategory, ProposalVersion, VoteWithWeight};
+
+  #[test]
+  fn test_to_table() {
+    let result = GetMinaProposalResultResponse {
+      proposal: Proposal {
+        id: 1,
+        key: "cftest-2".to_string(),
+        start_time: 0,
+        end_time: 100_000,
+        epoch: 1,
+        ledger_hash: Some("ledger".to_string()),
+        category: ProposalCategory::Core,
+        version: ProposalVersion::V2,
+        title: "Title".to_string(),
+        description: String::new(),
+        url: String::new(),
+        network: Network::Devnet,
+      },
+      total_stake_weight: Decimal::new(5, 0),
+      positive_stake_weight: Decimal::new(3, 0),
+      negative_stake_weight: Decimal::new(2, 0),
+      votes: vec![VoteWithWeight {
+        account: "A".to_string(),
+        hash: "1".to_string(),
+        memo: "cftest-2".to_string(),
+        height: 10,
+        status: BlockStatus::Canonical,
+        timestamp: 10_000,
+        nonce: 0,
+        weight: Decimal::new(3, 0),
+      }],
+      attestation: None,
+    };
+
+    let table = to_table(&result);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines[0], "Proposal 1 (cftest-2): Title");
+    assert_eq!(lines[1], "Total stake:    5");
+    assert_eq!(lines[5].split_whitespace().collect::<Vec<_>>(), ["ACCOUNT", "WEIGHT", "MEMO", "HEIGHT", "STATUS"]);
+    assert_eq!(lines[6].split_whitespace().collect::<Vec<_>>(), ["A", "3", "cftest-2", "10", "Canonical"]);
+  }
+
+  #[test]
+  fn test_to_proposals_table() {
+    let manifest: ProposalsManifest = serde_json::from_slice(include_bytes!("../proposals/proposals.json")).unwrap();
+    let table = to_proposals_table(&manifest);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), manifest.proposals.len() + 1);
+    assert_eq!(lines[0].split_whitespace().collect::<Vec<_>>(), ["ID", "KEY", "NETWORK", "START", "END"]);
+    let proposal = &manifest.proposals[0];
+    assert_eq!(lines[1].split_whitespace().collect::<Vec<_>>(), [
+      proposal.id.to_string(),
+      proposal.key.clone(),
+      proposal.network.to_string(),
+      proposal.start_time.to_string(),
+      proposal.end_time.to_string()
+    ]);
+  }
+
+  #[test]
+  fn test_vote_args() {
+    let memo = |args: &[&str]| {
+      VoteArgs::try_parse_from([&["vote"], args].concat()).map_err(|e| e.to_string())?.memo().map_err(|e| e.to_string())
+    };
+    let binary = |key: &str, yes| VoteMemo::Binary { key: key.to_string(), yes };
+
+    assert_eq!(memo(&["--proposal", "MIP3"]), Ok(binary("mip3", true)));
+    assert_eq!(memo(&["--proposal", "MIP3", "--no"]), Ok(binary("mip3", false)));
+    assert_eq!(
+      memo(&["--mef-round", "1", "--proposal", "2", "--yes"]),
+      Ok(VoteMemo::MefConsideration { round_id: 1, proposal_id: 2, yes: true })
+    );
+    assert_eq!(
+      memo(&["--mef-round", "1", "--rank", "3", "5", "2"]),
+      Ok(VoteMemo::MefRanked { round_id: 1, choices: vec!["3".to_string(), "5".to_string(), "2".to_string()] })
+    );
+
+    for args in [
+      &["--proposal", "MEF1 YES 2"][..],
+      &["--proposal", " "],
+      &["--proposal", "MIP3", "--yes"],
+      &["--mef-round", "1", "--proposal", "2"],
+      &["--mef-round", "1", "--proposal", "two", "--yes"],
+      &["--mef-round", "1", "--rank", "3", "x"],
+      &["--mef-round", "1", "--rank", "3", "--proposal", "2"],
+      &["--rank", "3"],
+      &["--mef-round", "1"],
+    ] {
+      assert!(memo(args).is_err(), "{args:?}");
+    }
+  }
+}
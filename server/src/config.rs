@@ -1,12 +1,12 @@
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{fs, path::PathBuf, str::FromStr, time::Duration};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use bytes::Bytes;
 use clap::{Args, Parser, ValueEnum};
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
 
-use crate::{Archive, Caches, Ocv, Proposal, ProposalsManifest};
+use crate::{Archive, ArchiveInterface, Caches, LedgerSource, Ocv, ProposalsManifest, S3LedgerSource};
 
 #[derive(Clone, Args)]
 pub struct OcvConfig {
@@ -22,29 +22,47 @@ pub struct OcvConfig {
   /// The connection URL for the archive database.
   #[clap(long, env)]
   pub archive_database_url: String,
-  /// Set the name of the bucket containing the ledgers
-  #[clap(long, env)]
-  pub bucket_name: String,
-  /// Path to store the ledgers
-  #[clap(long, env, default_value = "/tmp/ledgers")]
-  pub ledger_storage_path: String,
+  /// Seconds after which an archive query is cancelled.
+  #[clap(long, env, default_value = "30")]
+  pub archive_query_timeout: u64,
+  /// Staking ledger Args.
+  #[command(flatten)]
+  pub ledger: LedgerConfig,
+  /// Path to store the final results of closed proposals
+  #[clap(long, env, default_value = "/tmp/results")]
+  pub results_storage_path: String,
+  /// Depth below the chain tip at which a block is considered final.
+  #[clap(long, env, default_value = "10")]
+  pub finality_depth: i64,
 }
 
 impl OcvConfig {
   pub async fn to_ocv(&self) -> Result<Ocv> {
-    fs::create_dir_all(&self.ledger_storage_path)?;
+    self.to_ocv_with(Archive::new(&self.archive_database_url, Duration::from_secs(self.archive_query_timeout))).await
+  }
+
+  pub async fn to_ocv_with<A: ArchiveInterface>(&self, archive: A) -> Result<Ocv<A>> {
+    fs::create_dir_all(&self.ledger.ledger_storage_path)?;
+    fs::create_dir_all(&self.results_storage_path)?;
+    let manifest = self.load_manifest().await?;
     Ok(Ocv {
       caches: Caches::build(),
-      archive: Archive::new(&self.archive_database_url),
+      archive,
       network: self.network,
       release_stage: self.release_stage,
-      ledger_storage_path: PathBuf::from_str(&self.ledger_storage_path)?,
-      bucket_name: self.bucket_name.clone(),
-      proposals: self.load_proposals().await?,
+      ledger_storage_path: PathBuf::from_str(&self.ledger.ledger_storage_path)?,
+      results_storage_path: PathBuf::from_str(&self.results_storage_path)?,
+      ledger_source: self.ledger.source()?,
+      proposals: manifest.proposals,
+      rounds: manifest.rounds,
+      finality_depth: self.finality_depth,
+      require_ledger_manifests: self.ledger.require_ledger_manifests,
     })
   }
 
-  async fn load_proposals(&self) -> Result<Vec<Proposal>> {
+  /// The proposals and funding rounds of the manifest which are on this
+  /// network.
+  async fn load_manifest(&self) -> Result<ProposalsManifest> {
     let manifest_bytes = match &self.maybe_proposals_url {
       Some(url) => {
         let url = if url.is_empty() { &PROPOSALS_MANIFEST_GITHUB_URL.to_string() } else { url };
@@ -52,17 +70,68 @@ impl OcvConfig {
       }
       None => Bytes::from_static(include_bytes!("../proposals/proposals.json")),
     };
-    let manifest: ProposalsManifest = serde_json::from_slice(manifest_bytes.as_ref())?;
-    let filtered_by_network =
-      manifest.proposals.into_iter().filter(|proposal| proposal.network == self.network).collect();
-    Ok(filtered_by_network)
+    let mut manifest: ProposalsManifest = serde_json::from_slice(manifest_bytes.as_ref())?;
+    manifest.proposals.retain(|proposal| proposal.network == self.network);
+    manifest.rounds.retain(|round| round.network == self.network);
+    Ok(manifest)
+  }
+}
+
+/// Where staking ledgers are downloaded from and stored.
+#[derive(Clone, Args)]
+pub struct LedgerConfig {
+  /// Where the staking ledgers are downloaded from.
+  #[clap(long, env, value_enum, default_value_t = LedgerSourceKind::S3)]
+  pub ledger_source: LedgerSourceKind,
+  /// Set the name of the bucket containing the ledgers
+  #[clap(long, env, required_if_eq("ledger_source", "s3"))]
+  pub bucket_name: Option<String>,
+  /// The region of the ledger bucket.
+  #[clap(long, env, default_value = "us-west-2")]
+  pub bucket_region: String,
+  /// A custom S3 endpoint, such as a MinIO instance.
+  #[clap(long, env)]
+  pub bucket_endpoint: Option<String>,
+  /// Only consider ledgers under this key prefix.
+  #[clap(long, env)]
+  pub bucket_prefix: Option<String>,
+  /// The directory containing the ledgers, for the `directory` source.
+  #[clap(long, env, required_if_eq("ledger_source", "directory"))]
+  pub ledger_directory: Option<String>,
+  /// The base URL serving the ledgers, for the `http` source.
+  #[clap(long, env, required_if_eq("ledger_source", "http"))]
+  pub ledger_url: Option<String>,
+  /// Path to store the ledgers
+  #[clap(long, env, default_value = "/tmp/ledgers")]
+  pub ledger_storage_path: String,
+  /// Refuse ledgers which have no manifest to be verified against, rather
+  /// than using them with a warning.
+  #[clap(long, env)]
+  pub require_ledger_manifests: bool,
+}
+
+impl LedgerConfig {
+  pub fn source(&self) -> Result<LedgerSource> {
+    let missing = |arg| anyhow!("`{arg}` is required for the {} ledger source", self.ledger_source);
+    Ok(match self.ledger_source {
+      LedgerSourceKind::S3 => LedgerSource::S3(S3LedgerSource::new(
+        self.bucket_name.clone().ok_or_else(|| missing("bucket_name"))?,
+        self.bucket_region.clone(),
+        self.bucket_endpoint.clone(),
+        self.bucket_prefix.clone(),
+      )),
+      LedgerSourceKind::Directory => LedgerSource::Directory(PathBuf::from(
+        self.ledger_directory.as_ref().ok_or_else(|| missing("ledger_directory"))?,
+      )),
+      LedgerSourceKind::Http => LedgerSource::Http(self.ledger_url.clone().ok_or_else(|| missing("ledger_url"))?),
+    })
   }
 }
 
 static PROPOSALS_MANIFEST_GITHUB_URL: &str =
   "https://raw.githubusercontent.com/MinaFoundation/mina-on-chain-voting/main/server/proposals/proposals.json";
 
-#[derive(Clone, Copy, Parser, ValueEnum, Debug, Display, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Copy, Parser, ValueEnum, Debug, Display, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Network {
   #[display("mainnet")]
@@ -73,6 +142,17 @@ pub enum Network {
   Berkeley,
 }
 
+#[derive(Clone, Copy, Parser, ValueEnum, Debug, Display, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LedgerSourceKind {
+  #[display("s3")]
+  S3,
+  #[display("directory")]
+  Directory,
+  #[display("http")]
+  Http,
+}
+
 #[derive(Clone, Copy, Parser, ValueEnum, Debug, Display, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum ReleaseStage {
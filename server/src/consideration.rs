@@ -0,0 +1,112 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::VoteMemo;
+
+/// How a vote in the consideration phase of a MEF round was cast.
+///
+/// The memo grammar is that of [`VoteMemo::MefConsideration`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsiderationChoice {
+  Yes,
+  No,
+  /// The memo is not a vote on the proposal.
+  Invalid,
+}
+
+impl ConsiderationChoice {
+  /// Classifies a decoded memo as a vote on proposal `proposal_id` of round
+  /// `round_id`.
+  pub fn classify(memo: &str, round_id: usize, proposal_id: usize) -> Self {
+    match VoteMemo::parse(memo) {
+      Some(VoteMemo::MefConsideration { round_id: r, proposal_id: p, yes }) if r == round_id && p == proposal_id => {
+        if yes {
+          ConsiderationChoice::Yes
+        } else {
+          ConsiderationChoice::No
+        }
+      }
+      _ => ConsiderationChoice::Invalid,
+    }
+  }
+
+  /// The lowercase memo casting this choice, if it is a valid one.
+  pub fn memo(self, round_id: usize, proposal_id: usize) -> String {
+    let yes = match self {
+      ConsiderationChoice::Yes => true,
+      ConsiderationChoice::No => false,
+      ConsiderationChoice::Invalid => return String::new(),
+    };
+    VoteMemo::MefConsideration { round_id, proposal_id, yes }.to_string()
+  }
+}
+
+/// The headcount and stake of each choice among consideration votes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConsiderationTally {
+  pub positive_votes: usize,
+  pub negative_votes: usize,
+  pub invalid_votes: usize,
+  pub positive_stake: Decimal,
+  pub negative_stake: Decimal,
+}
+
+impl ConsiderationTally {
+  /// Tallies votes, each classified once and counted with its stake.
+  pub fn tally(votes: impl IntoIterator<Item = (ConsiderationChoice, Decimal)>) -> Self {
+    let mut tally = ConsiderationTally::default();
+    for (choice, stake) in votes {
+      tally.add(choice, stake);
+    }
+    tally
+  }
+
+  /// Counts one vote. The stake of an invalid vote is not counted.
+  pub fn add(&mut self, choice: ConsiderationChoice, stake: Decimal) {
+    match choice {
+      ConsiderationChoice::Yes => {
+        self.positive_votes += 1;
+        self.positive_stake += stake;
+      }
+      ConsiderationChoice::No => {
+        self.negative_votes += 1;
+        self.negative_stake += stake;
+      }
+      ConsiderationChoice::Invalid => self.invalid_votes += 1,
+    }
+  }
+
+  /// The stake of the valid votes.
+  pub fn total_stake(&self) -> Decimal {
+    self.positive_stake + self.negative_stake
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use proptest::prelude::*;
+
+  use super::*;
+
+  #[test]
+  fn test_classify() {
+    assert_eq!(ConsiderationChoice::classify("MEF1 YES 2", 1, 2), ConsiderationChoice::Yes);
+    assert_eq!(ConsiderationChoice::classify("mef1 no 2", 1, 2), ConsiderationChoice::No);
+    assert_eq!(ConsiderationChoice::classify("MEF 1 YES 2", 1, 2), ConsiderationChoice::Invalid);
+    assert_eq!(ConsiderationChoice::classify("MEF1 YES 21", 1, 2), ConsiderationChoice::Invalid);
+    assert_eq!(ConsiderationChoice::classify("MEF11 YES 2", 1, 2), ConsiderationChoice::Invalid);
+    assert_eq!(ConsiderationChoice::classify("", 1, 2), ConsiderationChoice::Invalid);
+  }
+
+  proptest! {
+    #[test]
+    fn test_classify_round_trips(round_id in 0usize .. 1000, proposal_id in 0usize .. 1000, yes: bool) {
+      let choice = if yes { ConsiderationChoice::Yes } else { ConsiderationChoice::No };
+      let memo = choice.memo(round_id, proposal_id).to_uppercase();
+      prop_assert_eq!(ConsiderationChoice::classify(&memo, round_id, proposal_id), choice);
+      prop_assert_eq!(ConsiderationChoice::classify(&memo, round_id + 1, proposal_id), ConsiderationChoice::Invalid);
+      prop_assert_eq!(ConsiderationChoice::classify(&memo, round_id, proposal_id + 1), ConsiderationChoice::Invalid);
+    }
+  }
+}
@@ -0,0 +1,97 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use clap::{ArgGroup, Parser};
+
+use crate::{Archive, Ledger, LedgerManifest};
+
+/// Builds a staking ledger from the archive database and writes it where
+/// `Ledger::fetch` looks for it.
+///
+/// The export is written without a manifest, as one computed from the export
+/// itself would verify nothing: it is used unverified, and refused with
+/// `--require-ledger-manifests`. See [`Archive::fetch_ledger_accounts`] for
+/// how it may differ from the actual staking ledger.
+#[derive(Clone, Parser)]
+#[clap(group(ArgGroup::new("ledger").required(true).args(["epoch", "ledger_hash"])))]
+pub struct ExportLedgerArgs {
+  /// Export the staking ledger of this epoch.
+  #[clap(long)]
+  pub epoch: Option<i64>,
+  /// Export the ledger with this hash.
+  #[clap(long)]
+  pub ledger_hash: Option<String>,
+  /// The connection URL for the archive database.
+  #[clap(long, env)]
+  pub archive_database_url: String,
+  /// Seconds after which an archive query is cancelled.
+  #[clap(long, env, default_value = "600")]
+  pub archive_query_timeout: u64,
+  /// Path to store the ledgers
+  #[clap(long, env, default_value = "/tmp/ledgers")]
+  pub ledger_storage_path: String,
+}
+
+impl ExportLedgerArgs {
+  pub async fn export(&self) -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let archive = Archive::new(&self.archive_database_url, Duration::from_secs(self.archive_query_timeout));
+    let hash = match (&self.ledger_hash, self.epoch) {
+      (Some(hash), _) => hash.clone(),
+      (None, Some(epoch)) => archive.fetch_staking_ledger_hash(epoch).await?,
+      (None, None) => unreachable!("clap requires either an epoch or a ledger hash"),
+    };
+    let accounts = archive.fetch_ledger_accounts(&hash).await?;
+    let path = self.write(&hash, Ledger::new(accounts))?;
+    tracing::info!("Exported ledger {hash} to {}, without a manifest to verify it against.", path.display());
+    Ok(())
+  }
+
+  fn write(&self, hash: &str, ledger: Ledger) -> Result<PathBuf> {
+    let dir = PathBuf::from(&self.ledger_storage_path);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{hash}.json"));
+    let manifest_path = LedgerManifest::path_for(&path);
+    if manifest_path.exists() {
+      fs::remove_file(manifest_path)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec(&ledger)?)?;
+    fs::rename(tmp, &path)?;
+    Ok(path)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::LedgerAccount;
+
+  #[test]
+  fn test_export_is_readable() {
+    let dir = std::env::temp_dir().join(format!("mina-ocv-export-{}", std::process::id()));
+    let args = ExportLedgerArgs {
+      epoch: None,
+      ledger_hash: Some("jxhash".to_string()),
+      archive_database_url: String::new(),
+      archive_query_timeout: 600,
+      ledger_storage_path: dir.to_string_lossy().into_owned(),
+    };
+    let ledger = Ledger::new(vec![
+      LedgerAccount::new("A".to_string(), "2".to_string(), Some("A".to_string())),
+      LedgerAccount::new("B".to_string(), "1.5".to_string(), Some("A".to_string())),
+    ]);
+
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("jxhash.manifest.json"), r#"{"ledgerHash":"jxhash","numAccounts":2,"totalCurrency":"3.5"}"#)
+      .unwrap();
+    let path = args.write("jxhash", ledger).unwrap();
+    let read = Ledger::read(&path).unwrap();
+    assert_eq!(read.accounts().len(), 2);
+    // A manifest left over from another copy of the ledger is not kept.
+    assert_eq!(LedgerManifest::read(&LedgerManifest::path_for(&path)).unwrap(), None);
+
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
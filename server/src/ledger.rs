@@ -1,53 +1,163 @@
-use std::{collections::HashMap, fs, io::Read, path::PathBuf};
+use std::{
+  cell::Cell,
+  collections::HashMap,
+  fmt,
+  fs::{self, File},
+  io::{self, BufReader},
+  path::{Path, PathBuf},
+  sync::OnceLock,
+  time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::{Result, anyhow};
-use flate2::read::GzDecoder;
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
-use tar::Archive;
+use serde::{
+  Deserialize, Deserializer, Serialize,
+  de::{SeqAccess, Visitor},
+};
+use thiserror::Error;
+
+use crate::{ArchiveInterface, LedgerSource, Ocv, ProposalVersion, Vote, Wrapper, sha256_hex};
+
+/// A staking ledger, indexed by public key and by delegate so that an account
+/// and its delegators can be looked up without scanning every account.
+///
+/// A public key may appear in several entries. Its balance is then the sum of
+/// all of them, whichever the proposal version, while its delegate is that of
+/// its first entry.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "Vec<LedgerAccount>", into = "Vec<LedgerAccount>")]
+pub struct Ledger {
+  accounts: Vec<LedgerAccount>,
+  balances: Vec<Decimal>,
+  by_pk: HashMap<String, Vec<usize>>,
+  by_delegate: HashMap<String, Vec<usize>>,
+  accounts_digest: OnceLock<String>,
+}
 
-use crate::{Ocv, ProposalVersion, Vote, Wrapper, s3_client};
+impl Ledger {
+  pub fn new(accounts: Vec<LedgerAccount>) -> Self {
+    let balances =
+      accounts.iter().map(|a| a.balance.parse().unwrap_or_else(|_| Decimal::new(0, LEDGER_BALANCE_SCALE))).collect();
+    let mut by_pk: HashMap<String, Vec<usize>> = HashMap::with_capacity(accounts.len());
+    let mut by_delegate: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, account) in accounts.iter().enumerate() {
+      by_pk.entry(account.pk.clone()).or_default().push(i);
+      if let Some(delegate) = account.delegate.as_ref().filter(|delegate| **delegate != account.pk) {
+        by_delegate.entry(delegate.clone()).or_default().push(i);
+      }
+    }
+    Ledger { accounts, balances, by_pk, by_delegate, accounts_digest: OnceLock::new() }
+  }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Ledger(pub Vec<LedgerAccount>);
+  /// SHA-256 of the accounts serialized as compact JSON. This is not the
+  /// digest of the file the ledger was read from, whose formatting may
+  /// differ, but it is the same whichever file the accounts come from.
+  pub fn accounts_digest(&self) -> &str {
+    self
+      .accounts_digest
+      .get_or_init(|| sha256_hex(&serde_json::to_vec(&self.accounts).expect("accounts serialize to JSON")))
+  }
 
-impl Ledger {
-  pub async fn fetch(ocv: &Ocv, hash: &String) -> Result<Ledger> {
-    let dest = ocv.ledger_storage_path.join(format!("{hash}.json"));
+  pub fn accounts(&self) -> &[LedgerAccount] {
+    &self.accounts
+  }
+
+  pub fn account(&self, public_key: &str) -> Option<&LedgerAccount> {
+    self.by_pk.get(public_key).map(|entries| &self.accounts[entries[0]])
+  }
+
+  /// The parsed balance of `public_key`, summed over all of its entries.
+  pub fn balance(&self, public_key: &str) -> Option<Decimal> {
+    let entries = self.by_pk.get(public_key)?;
+    Some(entries.iter().fold(Decimal::new(0, LEDGER_BALANCE_SCALE), |acc, &i| acc + self.balances[i]))
+  }
+
+  /// The accounts delegating their stake to `public_key`, excluding itself.
+  pub fn delegators<'a>(&'a self, public_key: &str) -> impl Iterator<Item = &'a LedgerAccount> + 'a {
+    self.by_delegate.get(public_key).into_iter().flatten().map(|&i| &self.accounts[i])
+  }
+
+  /// Reads the ledger for `hash` from `ledger_storage_path`, downloading it
+  /// first if needed.
+  pub async fn fetch<A: ArchiveInterface>(ocv: &Ocv<A>, hash: &str) -> Result<Ledger> {
+    Self::fetch_with(&ocv.ledger_source, &ocv.ledger_storage_path, ocv.require_ledger_manifests, hash).await
+  }
+
+  /// Reads the ledger for `hash` from `storage_path`, downloading it from
+  /// `source` first if needed. A local copy which cannot be parsed or does
+  /// not match its manifest is quarantined and downloaded again. A missing
+  /// manifest is only an error if `require_manifest`, and the copy is kept as
+  /// downloading again would not provide one.
+  pub async fn fetch_with(
+    source: &LedgerSource,
+    storage_path: &Path,
+    require_manifest: bool,
+    hash: &str,
+  ) -> Result<Ledger> {
+    let dest = storage_path.join(format!("{hash}.json"));
     if !dest.exists() {
-      Self::download(ocv, hash, &dest).await?;
+      source.fetch(hash, &dest).await?;
     }
-    let contents = fs::read(dest)?;
-    Ok(Ledger(serde_json::from_slice(&contents[..]).expect("Expecting a valid list of ledger accounts.")))
-  }
-
-  async fn download(ocv: &Ocv, hash: &String, to: &PathBuf) -> Result<()> {
-    let client = s3_client();
-    let s3_path = client
-      .list_objects_v2()
-      .bucket(&ocv.bucket_name)
-      .send()
-      .await?
-      .contents
-      .and_then(|objects| {
-        objects.into_iter().find(|object| object.key.as_ref().is_some_and(|key| key.contains(hash))).and_then(|x| x.key)
-      })
-      .ok_or(anyhow!("Could not retrieve dump corresponding to {hash}"))?;
-    let bytes =
-      client.get_object().bucket(&ocv.bucket_name).key(&s3_path).send().await?.body.collect().await?.into_bytes();
-    let tar_gz = GzDecoder::new(&bytes[..]);
-    let mut archive = Archive::new(tar_gz);
-    for entry in archive.entries()? {
-      let mut entry = entry?;
-      let path = entry.path()?.to_str().expect("Expecting a valid path").to_owned();
-      if s3_path.contains(&path) {
-        let mut buffer = Vec::new();
-        entry.read_to_end(&mut buffer)?;
-        fs::write(to, buffer)?;
-        break;
+    match Self::read_verified(hash, &dest, require_manifest) {
+      Ok(ledger) => Ok(ledger),
+      Err(e) if e.is_corrupt() => {
+        let quarantined = Self::quarantine(&dest)?;
+        tracing::warn!("{e}, moved to {} and downloading it again", quarantined.display());
+        source.fetch(hash, &dest).await?;
+        Ok(Self::read_verified(hash, &dest, require_manifest)?)
       }
+      Err(e) => Err(e.into()),
     }
-    Ok(())
+  }
+
+  /// Reads the ledger at `path` and checks it against the manifest stored
+  /// next to it, if there is one.
+  fn read_verified(hash: &str, path: &Path, require_manifest: bool) -> Result<Ledger, LedgerError> {
+    let ledger = Self::read(path)?;
+    match LedgerManifest::read(&LedgerManifest::path_for(path))? {
+      Some(manifest) => manifest.verify(hash, &ledger)?,
+      None if require_manifest => return Err(LedgerError::MissingManifest { hash: hash.to_string() }),
+      None => tracing::warn!("no manifest for ledger {hash}, using it unverified"),
+    }
+    Ok(ledger)
+  }
+
+  /// The sum of all account balances.
+  pub fn total_currency(&self) -> Decimal {
+    self.balances.iter().fold(Decimal::new(0, LEDGER_BALANCE_SCALE), |acc, balance| acc + balance)
+  }
+
+  /// Stream-parses and validates a ledger dump.
+  pub fn read(path: &Path) -> Result<Ledger, LedgerError> {
+    let io_error = |source| LedgerError::Io { path: path.to_path_buf(), source };
+    let file = File::open(path).map_err(io_error)?;
+    let index = Cell::new(0);
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(file));
+    let accounts = deserializer
+      .deserialize_seq(LedgerAccountsVisitor(&index))
+      .and_then(|accounts| deserializer.end().map(|()| accounts))
+      .map_err(|source| LedgerError::Malformed { path: path.to_path_buf(), index: index.get(), source })?;
+
+    for account in &accounts {
+      if account.balance.parse::<Decimal>().is_err() {
+        return Err(LedgerError::InvalidBalance {
+          path: path.to_path_buf(),
+          pk: account.pk.clone(),
+          balance: account.balance.clone(),
+        });
+      }
+    }
+
+    Ok(Ledger::new(accounts))
+  }
+
+  /// Moves an unreadable ledger dump aside, returning its new location.
+  pub fn quarantine(path: &Path) -> Result<PathBuf> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let quarantined = path.with_extension(format!("json.corrupt-{timestamp}"));
+    fs::rename(path, &quarantined)?;
+    Ok(quarantined)
   }
 
   pub fn get_stake_weight(
@@ -58,10 +168,7 @@ impl Ledger {
   ) -> Result<Decimal> {
     let public_key: String = public_key.into();
 
-    let account =
-      self.0.iter().find(|d| d.pk == public_key).ok_or_else(|| anyhow!("account {public_key} not found in ledger"))?;
-
-    let balance = account.balance.parse().unwrap_or_else(|_| Decimal::new(0, LEDGER_BALANCE_SCALE));
+    let (account, balance) = self.find(&public_key)?;
 
     match version {
       ProposalVersion::V1 => {
@@ -69,75 +176,105 @@ impl Ledger {
           return Ok(Decimal::new(0, LEDGER_BALANCE_SCALE));
         }
 
-        let delegators = self
-          .0
-          .iter()
-          .filter(|d| d.delegate.clone().unwrap_or(d.pk.clone()) == public_key && d.pk != public_key)
-          .collect::<Vec<&LedgerAccount>>();
-
-        if delegators.is_empty() {
-          return Ok(balance);
-        }
-
-        let stake_weight = delegators.iter().fold(Decimal::new(0, LEDGER_BALANCE_SCALE), |acc, x| {
-          x.balance.parse().unwrap_or_else(|_| Decimal::new(0, LEDGER_BALANCE_SCALE)) + acc
-        });
-
-        Ok(stake_weight + balance)
+        Ok(self.delegated_balance(&public_key, |_| true) + balance)
       }
-      ProposalVersion::V2 => {
-        let delegators = self
-          .0
-          .iter()
-          .filter(|d| {
-            d.delegate.clone().unwrap_or(d.pk.clone()) == public_key && d.pk != public_key && !map.0.contains_key(&d.pk)
-          })
-          .collect::<Vec<&LedgerAccount>>();
-
-        if delegators.is_empty() {
-          return Ok(balance);
-        }
-
-        let stake_weight = delegators.iter().fold(Decimal::new(0, LEDGER_BALANCE_SCALE), |acc, x| {
-          x.balance.parse().unwrap_or_else(|_| Decimal::new(0, LEDGER_BALANCE_SCALE)) + acc
-        });
-
-        Ok(stake_weight + balance)
+      // Tallies attribute the whole ledger at once with `allocate_stake` under
+      // V3, which gives a voter the same weight as V2.
+      ProposalVersion::V2 | ProposalVersion::V3 => {
+        Ok(self.delegated_balance(&public_key, |d| !map.0.contains_key(&d.pk)) + balance)
       }
     }
   }
 
-  pub fn get_stake_weight_mep(
-    &self,
-    _map: &Wrapper<HashMap<String, Vote>>,
-    public_key: impl Into<String>,
-  ) -> Result<Decimal> {
-    let public_key: String = public_key.into();
+  /// Attributes the balance of every account to the voter it counts for
+  /// under [`ProposalVersion::V3`]: the account itself if it voted, otherwise
+  /// its delegate if that voted. Balances of accounts whose public key
+  /// appears several times are all counted.
+  ///
+  /// As each balance goes to at most one voter, the weights sum to at most
+  /// [`Self::total_currency`].
+  pub fn allocate_stake(&self, map: &Wrapper<HashMap<String, Vote>>) -> HashMap<String, Decimal> {
+    let mut allocation: HashMap<String, Decimal> = HashMap::new();
+    for (account, balance) in self.accounts.iter().zip(&self.balances) {
+      let recipient = if map.0.contains_key(&account.pk) {
+        &account.pk
+      } else {
+        match &account.delegate {
+          Some(delegate) if map.0.contains_key(delegate) => delegate,
+          _ => continue,
+        }
+      };
+      *allocation.entry(recipient.clone()).or_insert(Decimal::new(0, LEDGER_BALANCE_SCALE)) += balance;
+    }
+    allocation
+  }
 
-    let account =
-      self.0.iter().find(|d| d.pk == public_key).ok_or_else(|| anyhow!("account {public_key} not found in ledger"))?;
+  /// The weight of `public_key` in MEF votes: its balance plus that of every
+  /// account delegating to it, whether or not those accounts voted
+  /// themselves. An account delegating elsewhere weighs nothing, so each
+  /// balance counts for at most one voter.
+  pub fn get_stake_weight_mep(&self, public_key: impl Into<String>) -> Result<Decimal> {
+    let public_key: String = public_key.into();
 
-    let balance = account.balance.parse().unwrap_or_else(|_| Decimal::new(0, LEDGER_BALANCE_SCALE));
+    let (account, balance) = self.find(&public_key)?;
 
     if account.delegate.clone().unwrap_or(public_key.clone()) != public_key {
       return Ok(Decimal::new(0, LEDGER_BALANCE_SCALE));
     }
 
-    let delegators = self
-      .0
-      .iter()
-      .filter(|d| d.delegate.clone().unwrap_or(d.pk.clone()) == public_key && d.pk != public_key)
-      .collect::<Vec<&LedgerAccount>>();
+    Ok(self.delegated_balance(&public_key, |_| true) + balance)
+  }
 
-    if delegators.is_empty() {
-      return Ok(balance);
+  /// The delegators whose balance [`Self::get_stake_weight`] adds to the
+  /// weight of `public_key`, with their balances.
+  pub fn stake_delegators(
+    &self,
+    map: &Wrapper<HashMap<String, Vote>>,
+    version: &ProposalVersion,
+    public_key: &str,
+  ) -> Vec<(&LedgerAccount, Decimal)> {
+    let delegates_away =
+      self.account(public_key).and_then(|account| account.delegate.as_deref()).is_some_and(|d| d != public_key);
+    if *version == ProposalVersion::V1 && delegates_away {
+      return Vec::new();
     }
+    self
+      .by_delegate
+      .get(public_key)
+      .into_iter()
+      .flatten()
+      .filter(|&&i| *version == ProposalVersion::V1 || !map.0.contains_key(&self.accounts[i].pk))
+      .map(|&i| (&self.accounts[i], self.balances[i]))
+      .collect()
+  }
+
+  fn find(&self, public_key: &str) -> Result<(&LedgerAccount, Decimal)> {
+    let not_found = || anyhow!("account {public_key} not found in ledger");
+    Ok((self.account(public_key).ok_or_else(not_found)?, self.balance(public_key).ok_or_else(not_found)?))
+  }
+
+  /// The total balance of the delegators of `public_key` which satisfy
+  /// `include`.
+  fn delegated_balance(&self, public_key: &str, include: impl Fn(&LedgerAccount) -> bool) -> Decimal {
+    self
+      .by_delegate
+      .get(public_key)
+      .into_iter()
+      .flatten()
+      .filter(|&&i| include(&self.accounts[i]))
+      .fold(Decimal::new(0, LEDGER_BALANCE_SCALE), |acc, &i| acc + self.balances[i])
+  }
+}
 
-    let stake_weight = delegators.iter().fold(Decimal::new(0, LEDGER_BALANCE_SCALE), |acc, x| {
-      x.balance.parse().unwrap_or_else(|_| Decimal::new(0, LEDGER_BALANCE_SCALE)) + acc
-    });
+impl From<Vec<LedgerAccount>> for Ledger {
+  fn from(accounts: Vec<LedgerAccount>) -> Self {
+    Ledger::new(accounts)
+  }
+}
 
-    Ok(stake_weight + balance)
+impl From<Ledger> for Vec<LedgerAccount> {
+  fn from(ledger: Ledger) -> Self {
+    ledger.accounts
   }
 }
 
@@ -157,6 +294,106 @@ impl LedgerAccount {
 
 pub const LEDGER_BALANCE_SCALE: u32 = 9;
 
+#[derive(Debug, Error)]
+pub enum LedgerError {
+  #[error("failed to read ledger {}: {source}", .path.display())]
+  Io { path: PathBuf, source: io::Error },
+  #[error("malformed ledger {} at account {index}: {source}", .path.display())]
+  Malformed { path: PathBuf, index: usize, source: serde_json::Error },
+  #[error("invalid balance {balance:?} for account {pk} in ledger {}", .path.display())]
+  InvalidBalance { path: PathBuf, pk: String, balance: String },
+  #[error("malformed manifest {}: {source}", .path.display())]
+  MalformedManifest { path: PathBuf, source: serde_json::Error },
+  #[error("no manifest to verify ledger {hash} against")]
+  MissingManifest { hash: String },
+  #[error("ledger {hash} does not match its manifest: expected {field} {expected}, found {found}")]
+  Mismatch { hash: String, field: &'static str, expected: String, found: String },
+}
+
+impl LedgerError {
+  /// Whether the local copy of the ledger is damaged or is not the ledger it
+  /// should be, so that downloading it again may help.
+  pub fn is_corrupt(&self) -> bool {
+    matches!(
+      self,
+      LedgerError::Malformed { .. }
+        | LedgerError::InvalidBalance { .. }
+        | LedgerError::MalformedManifest { .. }
+        | LedgerError::Mismatch { .. }
+    )
+  }
+}
+
+/// Metadata published alongside a staking ledger dump, used to check that the
+/// downloaded accounts are the ledger a proposal refers to.
+///
+/// Recomputing the Mina ledger hash would require the full account records,
+/// which the dumps do not contain, so only the aggregates are checked.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerManifest {
+  pub ledger_hash: String,
+  pub num_accounts: usize,
+  pub total_currency: Decimal,
+}
+
+impl LedgerManifest {
+  /// The location of the manifest for the ledger stored at `ledger_path`.
+  pub fn path_for(ledger_path: &Path) -> PathBuf {
+    ledger_path.with_extension("manifest.json")
+  }
+
+  /// Reads the manifest at `path`, if there is one.
+  pub fn read(path: &Path) -> Result<Option<LedgerManifest>, LedgerError> {
+    let contents = match fs::read(path) {
+      Ok(contents) => contents,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+      Err(source) => return Err(LedgerError::Io { path: path.to_path_buf(), source }),
+    };
+    serde_json::from_slice(&contents)
+      .map(Some)
+      .map_err(|source| LedgerError::MalformedManifest { path: path.to_path_buf(), source })
+  }
+
+  pub fn verify(&self, hash: &str, ledger: &Ledger) -> Result<(), LedgerError> {
+    let mismatch = |field, expected: String, found: String| {
+      Err(LedgerError::Mismatch { hash: hash.to_string(), field, expected, found })
+    };
+    if self.ledger_hash != hash {
+      return mismatch("ledger hash", self.ledger_hash.clone(), hash.to_string());
+    }
+    if self.num_accounts != ledger.accounts().len() {
+      return mismatch("account count", self.num_accounts.to_string(), ledger.accounts().len().to_string());
+    }
+    let total_currency = ledger.total_currency();
+    if self.total_currency != total_currency {
+      return mismatch("total currency", self.total_currency.to_string(), total_currency.to_string());
+    }
+    Ok(())
+  }
+}
+
+/// Deserializes a list of ledger accounts one at a time, keeping track of the
+/// index of the account being read.
+struct LedgerAccountsVisitor<'a>(&'a Cell<usize>);
+
+impl<'de> Visitor<'de> for LedgerAccountsVisitor<'_> {
+  type Value = Vec<LedgerAccount>;
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    formatter.write_str("a list of ledger accounts")
+  }
+
+  fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Self::Value, S::Error> {
+    let mut accounts = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+    while let Some(account) = seq.next_element()? {
+      accounts.push(account);
+      self.0.set(accounts.len());
+    }
+    Ok(accounts)
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -169,7 +406,7 @@ mod tests {
 
     // No account found - throw err.
     let error = Ledger::get_stake_weight(
-      &Ledger(vec![a.clone(), b.clone(), c.clone(), d.clone()]),
+      &Ledger::new(vec![a.clone(), b.clone(), c.clone(), d.clone()]),
       &Wrapper(map.clone()),
       &ProposalVersion::V1,
       "E",
@@ -178,7 +415,7 @@ mod tests {
 
     // Delegated stake away - returns 0.000000000.
     let d_weight = Ledger::get_stake_weight(
-      &Ledger(vec![a.clone(), b.clone(), c.clone(), d.clone()]),
+      &Ledger::new(vec![a.clone(), b.clone(), c.clone(), d.clone()]),
       &Wrapper(map.clone()),
       &ProposalVersion::V1,
       "D",
@@ -187,7 +424,7 @@ mod tests {
 
     // No delegators & delegated to self - returns balance.
     let b_weight = Ledger::get_stake_weight(
-      &Ledger(vec![a.clone(), b.clone(), c.clone(), d.clone()]),
+      &Ledger::new(vec![a.clone(), b.clone(), c.clone(), d.clone()]),
       &Wrapper(map.clone()),
       &ProposalVersion::V1,
       "B",
@@ -197,7 +434,7 @@ mod tests {
 
     // Delegated to self & has delegators - returns balance + delegators.
     let a_weight = Ledger::get_stake_weight(
-      &Ledger(vec![a.clone(), b.clone(), c.clone(), d.clone()]),
+      &Ledger::new(vec![a.clone(), b.clone(), c.clone(), d.clone()]),
       &Wrapper(map.clone()),
       &ProposalVersion::V1,
       "A",
@@ -212,7 +449,7 @@ mod tests {
 
     // No account found - throw err.
     let error = Ledger::get_stake_weight(
-      &Ledger(vec![a.clone(), b.clone(), c.clone(), d.clone(), e.clone()]),
+      &Ledger::new(vec![a.clone(), b.clone(), c.clone(), d.clone(), e.clone()]),
       &Wrapper(map.clone()),
       &ProposalVersion::V2,
       "F",
@@ -220,7 +457,7 @@ mod tests {
     assert!(error.is_err());
 
     let a_weight = Ledger::get_stake_weight(
-      &Ledger(vec![a.clone(), b.clone(), c.clone(), d.clone(), e.clone()]),
+      &Ledger::new(vec![a.clone(), b.clone(), c.clone(), d.clone(), e.clone()]),
       &Wrapper(map.clone()),
       &ProposalVersion::V2,
       "A",
@@ -228,7 +465,7 @@ mod tests {
     assert_eq!(a_weight.unwrap(), Decimal::new(2000000000, LEDGER_BALANCE_SCALE));
 
     let b_weight = Ledger::get_stake_weight(
-      &Ledger(vec![a.clone(), b.clone(), c.clone(), d.clone(), e.clone()]),
+      &Ledger::new(vec![a.clone(), b.clone(), c.clone(), d.clone(), e.clone()]),
       &Wrapper(map.clone()),
       &ProposalVersion::V2,
       "B",
@@ -237,6 +474,181 @@ mod tests {
     assert_eq!(b_weight.unwrap(), Decimal::new(2000000000, LEDGER_BALANCE_SCALE));
   }
 
+  #[test]
+  fn test_stake_weight_v3() {
+    let (a, b, c, d, e) = get_accounts();
+    let f = LedgerAccount::new("F".to_string(), "1".to_string(), Some("C".to_string()));
+    let a_again = LedgerAccount::new("A".to_string(), "2".to_string(), None);
+    let ledger = Ledger::new(vec![a, b, c, d, e, f, a_again]);
+    let mut map = get_votes();
+    map.remove("B");
+    map.insert("A".to_string(), Vote::new("A".to_string(), "", "", 1, BlockStatus::Canonical, 1, 0));
+    map.insert("E".to_string(), Vote::new("E".to_string(), "", "", 1, BlockStatus::Canonical, 1, 0));
+    let map = Wrapper(map);
+
+    // A gets both of its entries and D, C keeps its own stake and gets F,
+    // while E votes alone as its delegate B did not vote.
+    let allocation = ledger.allocate_stake(&map);
+    assert_eq!(allocation.len(), 3);
+    assert_eq!(allocation["A"], Decimal::new(4, 0));
+    assert_eq!(allocation["C"], Decimal::new(2, 0));
+    assert_eq!(allocation["E"], Decimal::new(1, 0));
+    assert!(allocation.values().sum::<Decimal>() <= ledger.total_currency());
+
+    for (pk, weight) in &allocation {
+      assert_eq!(ledger.get_stake_weight(&map, &ProposalVersion::V3, pk.as_str()).unwrap(), *weight);
+    }
+    // Every version counts both entries of A.
+    assert_eq!(ledger.balance("A"), Some(Decimal::new(3, 0)));
+    assert_eq!(ledger.get_stake_weight(&map, &ProposalVersion::V2, "A").unwrap(), Decimal::new(4, 0));
+    assert_eq!(ledger.get_stake_weight(&map, &ProposalVersion::V1, "A").unwrap(), Decimal::new(5, 0));
+    assert_eq!(ledger.get_stake_weight_mep("A").unwrap(), Decimal::new(5, 0));
+    assert!(ledger.get_stake_weight(&map, &ProposalVersion::V3, "G").is_err());
+  }
+
+  #[test]
+  fn test_stake_delegators() {
+    let (a, b, c, d, e) = get_accounts();
+    let ledger = Ledger::new(vec![a, b, c, d.clone(), e]);
+    let map = Wrapper(get_votes());
+    let pks = |version, pk| -> Vec<String> {
+      ledger.stake_delegators(&map, &version, pk).into_iter().map(|(account, _)| account.pk.clone()).collect()
+    };
+
+    assert_eq!(pks(ProposalVersion::V1, "A"), vec!["C", "D"]);
+    assert_eq!(pks(ProposalVersion::V1, "C"), Vec::<String>::new());
+    // C voted itself, so under V2 its stake stays with its own vote.
+    assert_eq!(pks(ProposalVersion::V2, "A"), vec!["D"]);
+    assert_eq!(ledger.stake_delegators(&map, &ProposalVersion::V2, "A"), vec![(&d, Decimal::new(1, 0))]);
+  }
+
+  #[test]
+  fn test_ledger_index() {
+    let (a, b, c, d, e) = get_accounts();
+    let self_delegated = LedgerAccount::new("F".to_string(), "1".to_string(), Some("F".to_string()));
+    let ledger = Ledger::new(vec![a.clone(), b.clone(), c.clone(), d.clone(), e.clone(), self_delegated.clone()]);
+
+    assert_eq!(ledger.account("C"), Some(&c));
+    assert_eq!(ledger.account("G"), None);
+    assert_eq!(ledger.delegators("A").collect::<Vec<_>>(), vec![&c, &d]);
+    assert_eq!(ledger.delegators("B").collect::<Vec<_>>(), vec![&e]);
+    assert_eq!(ledger.delegators("F").count(), 0);
+    assert_eq!(ledger.delegators("G").count(), 0);
+
+    let json = serde_json::to_string(&ledger).unwrap();
+    let accounts: Vec<LedgerAccount> = serde_json::from_str(&json).unwrap();
+    assert_eq!(accounts, ledger.accounts());
+    let ledger: Ledger = serde_json::from_str(&json).unwrap();
+    assert_eq!(ledger.delegators("A").count(), 2);
+  }
+
+  #[test]
+  fn test_read_ledger() {
+    let dir = std::env::temp_dir().join(format!("mina-ocv-ledger-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("valid.json");
+    fs::write(&path, r#"[{"pk":"A","balance":"1.5","delegate":null},{"pk":"B","balance":"2","delegate":"A"}]"#)
+      .unwrap();
+    let ledger = Ledger::read(&path).unwrap();
+    assert_eq!(ledger.accounts().len(), 2);
+    assert_eq!(ledger.delegators("A").count(), 1);
+
+    let path = dir.join("truncated.json");
+    fs::write(&path, r#"[{"pk":"A","balance":"1.5","delegate":null},{"pk":"B","bala"#).unwrap();
+    assert!(matches!(Ledger::read(&path), Err(LedgerError::Malformed { index: 1, .. })));
+
+    let path = dir.join("missing_field.json");
+    fs::write(&path, r#"[{"pk":"A","balance":"1"},{"pk":"B","balance":"1"},{"balance":"1"}]"#).unwrap();
+    assert!(matches!(Ledger::read(&path), Err(LedgerError::Malformed { index: 2, .. })));
+
+    let path = dir.join("trailing.json");
+    fs::write(&path, r#"[{"pk":"A","balance":"1"}] []"#).unwrap();
+    assert!(matches!(Ledger::read(&path), Err(LedgerError::Malformed { index: 1, .. })));
+
+    let path = dir.join("not_a_list.json");
+    fs::write(&path, r#"{"pk":"A","balance":"1"}"#).unwrap();
+    assert!(matches!(Ledger::read(&path), Err(LedgerError::Malformed { index: 0, .. })));
+
+    let path = dir.join("balance.json");
+    fs::write(&path, r#"[{"pk":"A","balance":"one"}]"#).unwrap();
+    let error = Ledger::read(&path).unwrap_err();
+    assert!(matches!(&error, LedgerError::InvalidBalance { pk, .. } if pk == "A"));
+
+    assert!(matches!(Ledger::read(&dir.join("missing.json")), Err(LedgerError::Io { .. })));
+
+    let quarantined = Ledger::quarantine(&path).unwrap();
+    assert!(!path.exists());
+    assert!(quarantined.exists());
+
+    fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn test_verify_ledger_manifest() {
+    let ledger = Ledger::new(vec![
+      LedgerAccount::new("A".to_string(), "1.5".to_string(), None),
+      LedgerAccount::new("B".to_string(), "2".to_string(), Some("A".to_string())),
+    ]);
+    let manifest =
+      LedgerManifest { ledger_hash: "jx".to_string(), num_accounts: 2, total_currency: Decimal::new(35, 1) };
+    assert!(manifest.verify("jx", &ledger).is_ok());
+
+    assert!(matches!(manifest.verify("jy", &ledger), Err(LedgerError::Mismatch { field: "ledger hash", .. })));
+    let manifest_with_count = LedgerManifest { num_accounts: 3, ..manifest.clone() };
+    assert!(matches!(
+      manifest_with_count.verify("jx", &ledger),
+      Err(LedgerError::Mismatch { field: "account count", .. })
+    ));
+    let manifest_with_currency = LedgerManifest { total_currency: Decimal::new(4, 0), ..manifest.clone() };
+    assert!(matches!(
+      manifest_with_currency.verify("jx", &ledger),
+      Err(LedgerError::Mismatch { field: "total currency", .. })
+    ));
+
+    let dir = std::env::temp_dir().join(format!("mina-ocv-manifest-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = LedgerManifest::path_for(&dir.join("jx.json"));
+    assert_eq!(path, dir.join("jx.manifest.json"));
+    assert_eq!(LedgerManifest::read(&path).unwrap(), None);
+    fs::write(&path, r#"{"ledgerHash":"jx","numAccounts":2,"totalCurrency":"3.5"}"#).unwrap();
+    assert_eq!(LedgerManifest::read(&path).unwrap(), Some(manifest));
+    fs::write(&path, r#"{"ledgerHash":"jx"}"#).unwrap();
+    assert!(matches!(LedgerManifest::read(&path), Err(LedgerError::MalformedManifest { .. })));
+    fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_fetch_with_quarantines_only_corrupt_ledgers() {
+    let dir = std::env::temp_dir().join(format!("mina-ocv-fetch-{}", std::process::id()));
+    let (mirror, storage) = (dir.join("mirror"), dir.join("storage"));
+    fs::create_dir_all(&mirror).unwrap();
+    fs::create_dir_all(&storage).unwrap();
+    let source = LedgerSource::Directory(mirror.clone());
+    let corrupt_copies = || {
+      fs::read_dir(&storage)
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().contains(".corrupt-"))
+        .count()
+    };
+    fs::write(mirror.join("jx.json"), r#"[{"pk":"A","balance":"1","delegate":null}]"#).unwrap();
+
+    // Without a manifest the ledger is used unverified, unless manifests are
+    // required, and kept as downloaded rather than quarantined either way.
+    assert_eq!(Ledger::fetch_with(&source, &storage, false, "jx").await.unwrap().accounts().len(), 1);
+    let error = Ledger::fetch_with(&source, &storage, true, "jx").await.unwrap_err();
+    assert!(matches!(error.downcast_ref(), Some(LedgerError::MissingManifest { .. })));
+    assert!(storage.join("jx.json").exists());
+    assert_eq!(corrupt_copies(), 0);
+
+    // A damaged copy is quarantined and downloaded again.
+    fs::write(mirror.join("jx.manifest.json"), r#"{"ledgerHash":"jx","numAccounts":1,"totalCurrency":"1"}"#).unwrap();
+    fs::write(storage.join("jx.json"), "[{").unwrap();
+    assert_eq!(Ledger::fetch_with(&source, &storage, false, "jx").await.unwrap().accounts().len(), 1);
+    assert_eq!(corrupt_copies(), 1);
+    fs::remove_dir_all(dir).unwrap();
+  }
+
   fn get_accounts() -> (LedgerAccount, LedgerAccount, LedgerAccount, LedgerAccount, LedgerAccount) {
     (
       LedgerAccount::new("A".to_string(), "1".to_string(), None),
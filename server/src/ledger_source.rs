@@ -0,0 +1,225 @@
+use std::{
+  fs::{self, File},
+  io,
+  path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow};
+use aws_sdk_s3::{
+  Client,
+  config::{Builder, Credentials, Region},
+};
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use tar::Archive;
+
+use crate::LedgerManifest;
+
+/// Where staking ledger dumps are downloaded from.
+///
+/// Every source stores a dump either as plain `.json` or as a `.tar.gz`
+/// containing it, optionally accompanied by a `{hash}.manifest.json`.
+#[derive(Clone, Debug)]
+pub enum LedgerSource {
+  S3(S3LedgerSource),
+  /// A directory holding the dumps, such as an offline mirror of the bucket.
+  Directory(PathBuf),
+  /// A base URL under which the dumps are served as `{hash}.tar.gz` or
+  /// `{hash}.json`.
+  Http(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct S3LedgerSource {
+  client: Client,
+  bucket: String,
+  prefix: Option<String>,
+}
+
+impl S3LedgerSource {
+  /// Builds a client for `region`, or for a custom `endpoint` such as a MinIO
+  /// instance. Credentials are read from `AWS_ACCESS_KEY_ID` and
+  /// `AWS_SECRET_ACCESS_KEY` if set, otherwise requests are unsigned.
+  pub fn new(bucket: String, region: String, endpoint: Option<String>, prefix: Option<String>) -> Self {
+    let mut config = Builder::new().region(Region::new(region)).behavior_version_latest();
+    if let Some(endpoint) = endpoint {
+      config = config.endpoint_url(endpoint).force_path_style(true);
+    }
+    if let (Ok(id), Ok(secret)) = (std::env::var("AWS_ACCESS_KEY_ID"), std::env::var("AWS_SECRET_ACCESS_KEY")) {
+      config = config.credentials_provider(Credentials::new(id, secret, None, None, "environment"));
+    }
+    S3LedgerSource { client: Client::from_conf(config.build()), bucket, prefix }
+  }
+
+  async fn find_key(&self, hash: &str) -> Result<Option<String>> {
+    let mut pages =
+      self.client.list_objects_v2().bucket(&self.bucket).set_prefix(self.prefix.clone()).into_paginator().send();
+    while let Some(page) = pages.next().await {
+      let key = page?.contents.into_iter().flatten().filter_map(|object| object.key).find(|key| is_dump_for(key, hash));
+      if key.is_some() {
+        return Ok(key);
+      }
+    }
+    Ok(None)
+  }
+
+  async fn get(&self, key: &str) -> Result<Option<Bytes>> {
+    match self.client.get_object().bucket(&self.bucket).key(key).send().await {
+      Ok(object) => Ok(Some(object.body.collect().await?.into_bytes())),
+      Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => Ok(None),
+      Err(e) => Err(e.into()),
+    }
+  }
+}
+
+impl LedgerSource {
+  /// Downloads the dump for `hash` and writes the ledger to `to`, along with
+  /// its manifest if the source has one.
+  pub async fn fetch(&self, hash: &str, to: &Path) -> Result<()> {
+    let manifest_name = format!("{hash}.manifest.json");
+    let (name, dump, manifest) = match self {
+      LedgerSource::S3(s3) => {
+        let key = s3.find_key(hash).await?.ok_or(anyhow!("Could not retrieve dump corresponding to {hash}"))?;
+        let manifest_key = match key.rsplit_once('/') {
+          Some((dir, _)) => format!("{dir}/{manifest_name}"),
+          None => manifest_name,
+        };
+        let dump = s3.get(&key).await?.ok_or(anyhow!("Dump {key} disappeared from the bucket"))?;
+        (key, dump, s3.get(&manifest_key).await?)
+      }
+      LedgerSource::Directory(dir) => {
+        let path = fs::read_dir(dir)?
+          .map(|entry| entry.map(|entry| entry.path()))
+          .collect::<io::Result<Vec<_>>>()?
+          .into_iter()
+          .find(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| is_dump_for(name, hash)))
+          .ok_or(anyhow!("Could not find a dump corresponding to {hash} in {}", dir.display()))?;
+        let manifest = match fs::read(dir.join(manifest_name)) {
+          Ok(manifest) => Some(manifest.into()),
+          Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+          Err(e) => return Err(e.into()),
+        };
+        (path.to_string_lossy().into_owned(), fs::read(&path)?.into(), manifest)
+      }
+      LedgerSource::Http(base) => {
+        let base = base.trim_end_matches('/');
+        let mut dump = None;
+        for name in [format!("{hash}.tar.gz"), format!("{hash}.json")] {
+          if let Some(bytes) = http_get(&format!("{base}/{name}")).await? {
+            dump = Some((name, bytes));
+            break;
+          }
+        }
+        let (name, dump) = dump.ok_or(anyhow!("Could not retrieve dump corresponding to {hash} from {base}"))?;
+        (name, dump, http_get(&format!("{base}/{manifest_name}")).await?)
+      }
+    };
+
+    let manifest_path = LedgerManifest::path_for(to);
+    match manifest {
+      Some(manifest) => fs::write(&manifest_path, manifest)?,
+      None if manifest_path.exists() => fs::remove_file(&manifest_path)?,
+      None => {}
+    }
+    write_ledger(&name, &dump, to)
+  }
+}
+
+/// Whether the object `name` is a dump of the ledger `hash`, rather than its
+/// manifest or an unrelated file.
+fn is_dump_for(name: &str, hash: &str) -> bool {
+  let file_name = name.rsplit('/').next().unwrap_or(name);
+  file_name.contains(hash)
+    && !file_name.ends_with(".manifest.json")
+    && (file_name.ends_with(".tar.gz") || file_name.ends_with(".json"))
+}
+
+/// Writes the ledger contained in the dump `name` to `to`. For an archive
+/// this is the entry whose path appears in `name`, or else the first `.json`
+/// entry.
+fn write_ledger(name: &str, dump: &[u8], to: &Path) -> Result<()> {
+  let tmp = to.with_extension("json.tmp");
+  if !name.ends_with(".tar.gz") {
+    fs::write(&tmp, dump)?;
+    fs::rename(tmp, to)?;
+    return Ok(());
+  }
+
+  let mut archive = Archive::new(GzDecoder::new(dump));
+  for entry in archive.entries()? {
+    let mut entry = entry?;
+    let path = entry.path()?.to_str().expect("Expecting a valid path").to_owned();
+    if name.contains(&path) || path.ends_with(".json") {
+      io::copy(&mut entry, &mut File::create(&tmp)?)?;
+      fs::rename(tmp, to)?;
+      return Ok(());
+    }
+  }
+  Err(anyhow!("Dump {name} does not contain a ledger"))
+}
+
+async fn http_get(url: &str) -> Result<Option<Bytes>> {
+  let response = reqwest::get(url).await?;
+  if response.status() == reqwest::StatusCode::NOT_FOUND {
+    return Ok(None);
+  }
+  Ok(Some(response.error_for_status()?.bytes().await?))
+}
+
+#[cfg(test)]
+mod tests {
+  use flate2::{Compression, write::GzEncoder};
+
+  use super::*;
+
+  const LEDGER: &str = r#"[{"pk":"A","balance":"1","delegate":null}]"#;
+  const MANIFEST: &str = r#"{"ledgerHash":"jxhash","numAccounts":1,"totalCurrency":"1"}"#;
+
+  fn get_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mina-ocv-source-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  fn tar_gz(entry: &str, contents: &str) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, entry, contents.as_bytes()).unwrap();
+    builder.into_inner().unwrap().finish().unwrap()
+  }
+
+  #[test]
+  fn test_is_dump_for() {
+    assert!(is_dump_for("ledgers/mainnet-jxhash-1.json.tar.gz", "jxhash"));
+    assert!(is_dump_for("jxhash.json", "jxhash"));
+    assert!(!is_dump_for("jxhash.manifest.json", "jxhash"));
+    assert!(!is_dump_for("jxhash/readme.txt", "jxhash"));
+    assert!(!is_dump_for("jxother.json.tar.gz", "jxhash"));
+  }
+
+  #[tokio::test]
+  async fn test_directory_source() {
+    let mirror = get_dir("mirror");
+    let storage = get_dir("storage");
+    let source = LedgerSource::Directory(mirror.clone());
+    let to = storage.join("jxhash.json");
+
+    assert!(source.fetch("jxhash", &to).await.is_err());
+
+    fs::write(mirror.join("mainnet-jxhash.json.tar.gz"), tar_gz("mainnet-jxhash.json", LEDGER)).unwrap();
+    source.fetch("jxhash", &to).await.unwrap();
+    assert_eq!(fs::read_to_string(&to).unwrap(), LEDGER);
+    assert!(!LedgerManifest::path_for(&to).exists());
+
+    fs::write(mirror.join("jxhash.manifest.json"), MANIFEST).unwrap();
+    source.fetch("jxhash", &to).await.unwrap();
+    assert_eq!(fs::read_to_string(LedgerManifest::path_for(&to)).unwrap(), MANIFEST);
+
+    fs::remove_dir_all(mirror).unwrap();
+    fs::remove_dir_all(storage).unwrap();
+  }
+}
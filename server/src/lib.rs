@@ -1,23 +1,43 @@
 mod archive;
+mod attestation;
+mod bundle;
+mod commands;
 mod config;
+mod consideration;
+mod export_ledger;
 mod ledger;
+mod ledger_source;
+mod live;
+mod memo;
 mod ocv;
 mod proposals;
 mod ranked_vote;
 mod ranked_vote_builder;
 mod ranked_vote_config;
+mod results;
 mod serve;
 mod util;
 mod vote;
+mod vote_payment;
 
 pub use archive::*;
+pub use attestation::*;
+pub use bundle::*;
+pub use commands::*;
 pub use config::*;
+pub use consideration::*;
+pub use export_ledger::*;
 pub use ledger::*;
+pub use ledger_source::*;
+pub use live::*;
+pub use memo::*;
 pub use ocv::*;
 pub use proposals::*;
 pub use ranked_vote::*;
 pub use ranked_vote_builder::*;
 pub use ranked_vote_config::*;
+pub use results::*;
 pub use serve::*;
 pub use util::*;
 pub use vote::*;
+pub use vote_payment::*;
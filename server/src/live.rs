@@ -0,0 +1,193 @@
+use std::{
+  collections::{HashMap, HashSet},
+  convert::Infallible,
+  sync::{Arc, Mutex, Weak},
+  time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use axum::response::sse::Event;
+use futures_util::{Stream, StreamExt, stream};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{sync::watch, time};
+
+use crate::{ArchiveInterface, Ocv};
+
+/// What a live stream follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LiveTopic {
+  Proposal(usize),
+  /// The consideration of every candidate of a funding round, and its
+  /// ranked vote.
+  Round(usize),
+}
+
+/// The votes and tally of a topic as of a chain tip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveSnapshot {
+  pub chain_tip: i64,
+  /// Every vote counted, keyed by transaction hash.
+  pub votes: Vec<(String, Value)>,
+  /// The tally, as served by the topic's endpoints.
+  pub tally: Value,
+}
+
+#[derive(Serialize)]
+struct LiveTally<'a> {
+  chain_tip: i64,
+  tally: &'a Value,
+}
+
+type SnapshotSender = watch::Sender<Option<Arc<LiveSnapshot>>>;
+type SnapshotReceiver = watch::Receiver<Option<Arc<LiveSnapshot>>>;
+
+/// Live tallies, refreshed by a single poller of the archive whichever the
+/// number of subscribers.
+///
+/// The chain tip is polled every `interval` while there are subscribers. When
+/// it moves, the tally of every topic with a subscriber is computed once and
+/// sent to all of them. Tallies read the archive through the same ingestion as
+/// the endpoints, which evicts the cached votes of a topic when the read
+/// changes them, so polling evicts nothing itself.
+pub struct LiveTallies<A: ArchiveInterface> {
+  ocv: Arc<Ocv<A>>,
+  interval: Duration,
+  topics: Mutex<HashMap<LiveTopic, SnapshotSender>>,
+}
+
+impl<A: ArchiveInterface> LiveTallies<A> {
+  /// Creates the live tallies of `ocv` and spawns their poller, which stops
+  /// once they are dropped.
+  pub fn spawn(ocv: Arc<Ocv<A>>, interval: Duration) -> Arc<Self> {
+    let live = Arc::new(LiveTallies { ocv, interval, topics: Mutex::new(HashMap::new()) });
+    tokio::spawn(Self::poll(Arc::downgrade(&live)));
+    live
+  }
+
+  /// Subscribes to a topic. The first snapshot is sent on the next poll, or
+  /// right away if another subscriber already has one.
+  pub fn subscribe(&self, topic: LiveTopic) -> Result<SnapshotReceiver> {
+    match topic {
+      LiveTopic::Proposal(id) if !self.ocv.proposals.iter().any(|proposal| proposal.id == id) => {
+        return Err(anyhow!("Proposal {id} dne."));
+      }
+      LiveTopic::Round(id) => {
+        self.ocv.round(id)?;
+      }
+      LiveTopic::Proposal(_) => {}
+    }
+    let mut topics = self.topics.lock().map_err(|_| anyhow!("live topics lock poisoned"))?;
+    let mut receiver = topics.entry(topic).or_insert_with(|| watch::Sender::new(None)).subscribe();
+    receiver.mark_changed();
+    Ok(receiver)
+  }
+
+  async fn poll(live: Weak<Self>) {
+    let mut chain_tip = None;
+    loop {
+      let Some(live) = live.upgrade() else { return };
+      let interval = live.interval;
+      if live.has_subscribers() {
+        match live.ocv.archive.fetch_chain_tip().await {
+          Ok(tip) => {
+            let moved = chain_tip != Some(tip);
+            chain_tip = Some(tip);
+            live.refresh(tip, moved).await;
+          }
+          Err(error) => tracing::warn!("failed to poll the chain tip: {error}"),
+        }
+      }
+      drop(live);
+      time::sleep(interval).await;
+    }
+  }
+
+  /// Whether any topic has a subscriber, forgetting those which have none.
+  fn has_subscribers(&self) -> bool {
+    let Ok(mut topics) = self.topics.lock() else { return false };
+    topics.retain(|_, sender| sender.receiver_count() > 0);
+    !topics.is_empty()
+  }
+
+  /// Sends a snapshot to the subscribers of every topic, or only of the new
+  /// ones if the chain tip did not move.
+  async fn refresh(&self, chain_tip: i64, moved: bool) {
+    let topics: Vec<(LiveTopic, SnapshotSender)> = {
+      let Ok(mut topics) = self.topics.lock() else { return };
+      topics.retain(|_, sender| sender.receiver_count() > 0);
+      topics
+        .iter()
+        .filter(|(_, sender)| moved || sender.borrow().is_none())
+        .map(|(topic, sender)| (*topic, sender.clone()))
+        .collect()
+    };
+    for (topic, sender) in topics {
+      match self.ocv.live_snapshot(topic, chain_tip).await {
+        Ok(snapshot) => {
+          sender.send_replace(Some(Arc::new(snapshot)));
+        }
+        Err(error) => tracing::warn!("failed to refresh {:?}: {error}", topic),
+      }
+    }
+  }
+}
+
+/// The server-sent events of a subscription: a `votes` event with the votes
+/// not sent yet, if any, then a `tally` event, for every snapshot.
+pub fn live_events(receiver: SnapshotReceiver) -> impl Stream<Item = Result<Event, Infallible>> {
+  stream::unfold((receiver, HashSet::new()), |(mut receiver, mut sent)| async move {
+    loop {
+      receiver.changed().await.ok()?;
+      let Some(snapshot) = receiver.borrow_and_update().clone() else { continue };
+      let events = snapshot_events(&snapshot, &mut sent);
+      return Some((stream::iter(events), (receiver, sent)));
+    }
+  })
+  .flatten()
+}
+
+fn snapshot_events(snapshot: &LiveSnapshot, sent: &mut HashSet<String>) -> Vec<Result<Event, Infallible>> {
+  let votes: Vec<&Value> =
+    snapshot.votes.iter().filter(|(hash, _)| sent.insert(hash.clone())).map(|(_, vote)| vote).collect();
+  let mut events = Vec::new();
+  if !votes.is_empty() {
+    events.extend(Event::default().event("votes").json_data(votes).ok());
+  }
+  let tally = LiveTally { chain_tip: snapshot.chain_tip, tally: &snapshot.tally };
+  events.extend(Event::default().event("tally").json_data(tally).ok());
+  events.into_iter().map(Ok).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn test_snapshot_events() {
+    let snapshot = |chain_tip, hashes: &[&str]| LiveSnapshot {
+      chain_tip,
+      votes: hashes.iter().map(|hash| (hash.to_string(), json!({ "hash": hash }))).collect(),
+      tally: json!({ "total": hashes.len() }),
+    };
+    let render = |events: Vec<Result<Event, Infallible>>| {
+      events.into_iter().map(|event| format!("{:?}", event.unwrap())).collect()
+    };
+
+    let mut sent = HashSet::new();
+    let first: Vec<String> = render(snapshot_events(&snapshot(10, &["a1", "b1"]), &mut sent));
+    assert_eq!(first.len(), 2);
+    assert!(first[0].contains("votes") && first[0].contains("a1") && first[0].contains("b1"));
+    assert!(first[1].contains("tally") && first[1].contains("chain_tip"));
+
+    let second: Vec<String> = render(snapshot_events(&snapshot(11, &["a1", "b1", "c1"]), &mut sent));
+    assert_eq!(second.len(), 2);
+    assert!(second[0].contains("c1") && !second[0].contains("a1"));
+
+    let third: Vec<String> = render(snapshot_events(&snapshot(12, &["a1", "b1", "c1"]), &mut sent));
+    assert_eq!(third.len(), 1);
+    assert!(third[0].contains("tally"));
+  }
+}
@@ -0,0 +1,337 @@
+use std::fmt;
+
+use anyhow::{Result, ensure};
+use thiserror::Error;
+
+pub(crate) const MEMO_VERSION_BYTE: u8 = 0x14;
+pub(crate) const MEMO_USER_TAG: u8 = 0x01;
+pub(crate) const MEMO_DIGEST_TAG: u8 = 0x00;
+pub(crate) const MEMO_MAX_LENGTH: usize = 32;
+
+/// Decodes the text of a base58 user memo, checking that it is well formed.
+pub fn decode_memo(memo: &str) -> Result<String, MemoError> {
+  let error = |kind| MemoError { memo: memo.to_string(), kind };
+  let decoded =
+    bs58::decode(memo).with_check(None).into_vec().map_err(|source| error(MemoErrorKind::Base58(source)))?;
+
+  let (version, bytes) = decoded.split_first().ok_or_else(|| error(MemoErrorKind::Length(0)))?;
+  if *version != MEMO_VERSION_BYTE {
+    return Err(error(MemoErrorKind::Version(*version)));
+  }
+  if bytes.len() != MEMO_BYTES_LENGTH {
+    return Err(error(MemoErrorKind::Length(bytes.len())));
+  }
+  match bytes[0] {
+    MEMO_USER_TAG => {}
+    MEMO_DIGEST_TAG => return Err(error(MemoErrorKind::Digest)),
+    tag => return Err(error(MemoErrorKind::Tag(tag))),
+  }
+  let length = bytes[1];
+  let text = bytes.get(2 .. length as usize + 2).ok_or_else(|| error(MemoErrorKind::TextLength(length)))?;
+
+  String::from_utf8(text.to_vec()).map_err(|_| error(MemoErrorKind::Utf8))
+}
+
+/// A memo which could not be decoded.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("failed to decode memo {memo}: {kind}")]
+pub struct MemoError {
+  pub memo: String,
+  pub kind: MemoErrorKind,
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum MemoErrorKind {
+  #[error("not base58check ({0})")]
+  Base58(bs58::decode::Error),
+  #[error("version byte {0:#04x}, expected {MEMO_VERSION_BYTE:#04x}")]
+  Version(u8),
+  #[error("{0} bytes long, expected {MEMO_BYTES_LENGTH}")]
+  Length(usize),
+  #[error("a digest, not text")]
+  Digest,
+  #[error("unknown type tag {0:#04x}")]
+  Tag(u8),
+  #[error("{0} bytes of text, at most {MEMO_MAX_LENGTH} fit")]
+  TextLength(u8),
+  #[error("text is not UTF-8")]
+  Utf8,
+}
+
+/// The length of a memo in a transaction: a tag, a length and the padded text.
+pub const MEMO_BYTES_LENGTH: usize = MEMO_MAX_LENGTH + 2;
+
+/// Encodes text as a base58 user memo, the inverse of [`decode_memo`].
+pub fn encode_memo(text: &str) -> Result<String> {
+  Ok(bs58::encode(memo_bytes(text)?).with_check_version(MEMO_VERSION_BYTE).into_string())
+}
+
+/// The memo of a transaction carrying `text`.
+pub fn memo_bytes(text: &str) -> Result<[u8; MEMO_BYTES_LENGTH]> {
+  ensure!(text.len() <= MEMO_MAX_LENGTH, "memo {text} is longer than {MEMO_MAX_LENGTH} bytes");
+  let mut bytes = [0; MEMO_BYTES_LENGTH];
+  bytes[0] = MEMO_USER_TAG;
+  bytes[1] = text.len() as u8;
+  bytes[2 .. text.len() + 2].copy_from_slice(text.as_bytes());
+  Ok(bytes)
+}
+
+/// A vote, as cast in the decoded text of a memo.
+///
+/// Memos are matched case-insensitively and regardless of how their words are
+/// spaced, except that they must start with their first word: the archive is
+/// searched for votes by the prefix of their memo, so a memo starting with
+/// whitespace is never counted as a vote. The grammar, in order of
+/// precedence, is:
+///
+/// - `MEF{round_id} YES|NO {proposal_id}`: a MEF consideration vote.
+/// - `MEF {round_id} {proposal_id}...`: a MEF ranked vote, most preferred
+///   first.
+/// - `[NO] {key}`: a vote for or against the proposal `key`.
+///
+/// Ids are decimal numbers without leading zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VoteMemo {
+  Binary { key: String, yes: bool },
+  MefConsideration { round_id: usize, proposal_id: usize, yes: bool },
+  MefRanked { round_id: usize, choices: Vec<String> },
+}
+
+impl VoteMemo {
+  /// Parses a decoded memo, returning `None` if it is empty or starts with
+  /// whitespace, as such a memo cannot be told apart from any other text
+  /// when searching the archive.
+  pub fn parse(decoded: &str) -> Option<Self> {
+    if decoded.starts_with(char::is_whitespace) {
+      return None;
+    }
+    let words: Vec<String> = decoded.split_whitespace().map(str::to_lowercase).collect();
+    match words.as_slice() {
+      [] => None,
+      [round, choice, proposal_id] if round.starts_with("mef") && (choice == "yes" || choice == "no") => {
+        match (parse_id(&round["mef".len() ..]), parse_id(proposal_id)) {
+          (Some(round_id), Some(proposal_id)) => {
+            Some(VoteMemo::MefConsideration { round_id, proposal_id, yes: choice == "yes" })
+          }
+          _ => Some(VoteMemo::binary(&words)),
+        }
+      }
+      [mef, round_id, choices @ ..] if mef == "mef" => {
+        match (parse_id(round_id), choices.iter().all(|choice| parse_id(choice).is_some())) {
+          (Some(round_id), true) => Some(VoteMemo::MefRanked { round_id, choices: choices.to_vec() }),
+          _ => Some(VoteMemo::binary(&words)),
+        }
+      }
+      _ => Some(VoteMemo::binary(&words)),
+    }
+  }
+
+  fn binary(words: &[String]) -> Self {
+    match words {
+      [no, key @ ..] if no == "no" && !key.is_empty() => VoteMemo::Binary { key: key.join(" "), yes: false },
+      _ => VoteMemo::Binary { key: words.join(" "), yes: true },
+    }
+  }
+
+  /// Whether this is a vote for or against the proposal `key`.
+  pub fn is_binary_vote_on(&self, key: &str) -> bool {
+    match self {
+      VoteMemo::Binary { key: voted, .. } => *voted == normalize(key),
+      _ => false,
+    }
+  }
+
+  /// Encodes the canonical text of this memo as a base58 memo.
+  pub fn encode(&self) -> Result<String> {
+    encode_memo(&self.to_string())
+  }
+
+  /// The transaction memo carrying the canonical text of this memo.
+  pub fn to_bytes(&self) -> Result<[u8; MEMO_BYTES_LENGTH]> {
+    memo_bytes(&self.to_string())
+  }
+}
+
+/// The canonical text of the memo: lowercase, with single spaces.
+impl fmt::Display for VoteMemo {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      VoteMemo::Binary { key, yes: true } => write!(f, "{key}"),
+      VoteMemo::Binary { key, yes: false } => write!(f, "no {key}"),
+      VoteMemo::MefConsideration { round_id, proposal_id, yes } => {
+        write!(f, "mef{round_id} {} {proposal_id}", if *yes { "yes" } else { "no" })
+      }
+      VoteMemo::MefRanked { round_id, choices } => {
+        write!(f, "mef {round_id}")?;
+        choices.iter().try_for_each(|choice| write!(f, " {choice}"))
+      }
+    }
+  }
+}
+
+fn parse_id(word: &str) -> Option<usize> {
+  let canonical = word == "0" || (!word.starts_with('0') && !word.is_empty());
+  if canonical && word.bytes().all(|byte| byte.is_ascii_digit()) { word.parse().ok() } else { None }
+}
+
+fn normalize(text: &str) -> String {
+  text.split_whitespace().map(str::to_lowercase).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+  use proptest::prelude::*;
+
+  use super::*;
+  use crate::{BlockStatus, RankedVote, Vote, Wrapper};
+
+  #[test]
+  fn test_decode_memo() {
+    assert_eq!(decode_memo("E4Yf92G48v8FApR4EWQq3iKb2vZkHHxZHPaZ73NQNBXmHeXNzHHSp").unwrap(), "Payment#0");
+    assert_eq!(decode_memo("E4YkwtLx9t8gRCWWoc8cACHxKFSywt23uaGKTfmkwF1sNMh87FMEi").unwrap(), "MEF 1 3 1 39");
+
+    let kind = |memo: &str| decode_memo(memo).unwrap_err().kind;
+    assert!(matches!(kind("0OIl"), MemoErrorKind::Base58(_)));
+    assert!(matches!(kind(""), MemoErrorKind::Base58(_)));
+    assert!(matches!(kind("E4Yf92G48v8FApR4EWQq3iKb2vZkHHxZHPaZ73NQNBXmHeXNzHHSq"), MemoErrorKind::Base58(_)));
+
+    let text = memo_bytes("Payment#0").unwrap();
+    let encode = |version: u8, bytes: &[u8]| bs58::encode(bytes).with_check_version(version).into_string();
+    assert_eq!(kind(&encode(0x15, &text)), MemoErrorKind::Version(0x15));
+    assert_eq!(kind(&encode(MEMO_VERSION_BYTE, &text[.. 33])), MemoErrorKind::Length(33));
+    assert_eq!(kind(&bs58::encode([]).with_check().into_string()), MemoErrorKind::Length(0));
+
+    let with = |index: usize, byte: u8| {
+      let mut bytes = text;
+      bytes[index] = byte;
+      encode(MEMO_VERSION_BYTE, &bytes)
+    };
+    assert_eq!(kind(&with(0, MEMO_DIGEST_TAG)), MemoErrorKind::Digest);
+    assert_eq!(kind(&with(0, 0x02)), MemoErrorKind::Tag(0x02));
+    assert_eq!(kind(&with(1, 33)), MemoErrorKind::TextLength(33));
+    assert_eq!(kind(&with(1, 255)), MemoErrorKind::TextLength(255));
+    assert_eq!(kind(&with(2, 0xff)), MemoErrorKind::Utf8);
+  }
+
+  #[test]
+  fn test_encode_memo() {
+    assert_eq!(encode_memo("Payment#0").unwrap(), "E4Yf92G48v8FApR4EWQq3iKb2vZkHHxZHPaZ73NQNBXmHeXNzHHSp");
+    assert_eq!(encode_memo("MEF1 YES 1").unwrap(), "E4Yh4PzVLrCiugdoaASo5Ve6Do755ey6vGqkURC8z7qcADqMUcp9K");
+    assert!(encode_memo(&"a".repeat(MEMO_MAX_LENGTH + 1)).is_err());
+  }
+
+  #[test]
+  fn test_memo_bytes() {
+    let bytes = VoteMemo::MefConsideration { round_id: 1, proposal_id: 1, yes: true }.to_bytes().unwrap();
+    assert_eq!(&bytes[.. 12], b"\x01\x0amef1 yes 1");
+    assert!(bytes[12 ..].iter().all(|byte| *byte == 0));
+    assert_eq!(memo_bytes("").unwrap(), [&[1, 0][..], &[0; MEMO_MAX_LENGTH]].concat().as_slice());
+  }
+
+  #[test]
+  fn test_parse() {
+    let binary = |key: &str, yes| Some(VoteMemo::Binary { key: key.to_string(), yes });
+    assert_eq!(VoteMemo::parse("cftest-2"), binary("cftest-2", true));
+    assert_eq!(VoteMemo::parse("No   CFTest-2 "), binary("cftest-2", false));
+    assert_eq!(VoteMemo::parse("  No   CFTest-2"), None);
+    assert_eq!(VoteMemo::parse("no"), binary("no", true));
+    assert_eq!(
+      VoteMemo::parse("MEF1 YES 2"),
+      Some(VoteMemo::MefConsideration { round_id: 1, proposal_id: 2, yes: true })
+    );
+    assert_eq!(
+      VoteMemo::parse("mef1\tno  2"),
+      Some(VoteMemo::MefConsideration { round_id: 1, proposal_id: 2, yes: false })
+    );
+    assert_eq!(VoteMemo::parse("MEF01 YES 2"), binary("mef01 yes 2", true));
+    assert_eq!(VoteMemo::parse("MEF 1 YES 2"), binary("mef 1 yes 2", true));
+    assert_eq!(VoteMemo::parse("MEF 1 3 01"), binary("mef 1 3 01", true));
+    assert_eq!(
+      VoteMemo::parse("MEF 1 3 1 39"),
+      Some(VoteMemo::MefRanked { round_id: 1, choices: vec!["3".to_string(), "1".to_string(), "39".to_string()] })
+    );
+    assert_eq!(VoteMemo::parse("MEF x 3"), binary("mef x 3", true));
+    assert_eq!(VoteMemo::parse(" \t "), None);
+  }
+
+  #[test]
+  fn test_is_binary_vote_on() {
+    assert!(VoteMemo::parse("NO  cftest-2").unwrap().is_binary_vote_on("CFTest-2"));
+    assert!(!VoteMemo::parse("cftest-3").unwrap().is_binary_vote_on("cftest-2"));
+    assert!(!VoteMemo::parse("MEF1 YES 2").unwrap().is_binary_vote_on("mef1 yes 2"));
+  }
+
+  fn word() -> impl Strategy<Value = String> {
+    "[a-z0-9#-]{1,6}".prop_filter("not a keyword", |word| !["mef", "no", "yes"].contains(&word.as_str()))
+  }
+
+  fn vote_memo() -> impl Strategy<Value = VoteMemo> {
+    prop_oneof![
+      (prop::collection::vec(word(), 1 .. 4), any::<bool>())
+        .prop_map(|(words, yes)| VoteMemo::Binary { key: words.join(" "), yes }),
+      (0usize .. 1000, 0usize .. 1000, any::<bool>())
+        .prop_map(|(round_id, proposal_id, yes)| VoteMemo::MefConsideration { round_id, proposal_id, yes }),
+      (0usize .. 1000, prop::collection::vec(0usize .. 1000, 0 .. 5)).prop_map(|(round_id, choices)| {
+        VoteMemo::MefRanked { round_id, choices: choices.iter().map(usize::to_string).collect() }
+      }),
+    ]
+  }
+
+  /// Memos as found in the archive: mostly well formed, with arbitrary text.
+  fn archive_memo() -> impl Strategy<Value = String> {
+    prop_oneof![
+      "\\PC{0,60}",
+      "(?i)(no |mef[0-9] (yes|no) |mef [0-9] )?[a-z0-9 ]{0,20}"
+        .prop_filter_map("too long", |text| encode_memo(&text).ok()),
+      "\\PC{0,32}".prop_filter_map("too long", |text| encode_memo(&text).ok()),
+    ]
+  }
+
+  proptest! {
+    #[test]
+    fn test_vote_memo_round_trips(memo in vote_memo()) {
+      let text = memo.to_string();
+      prop_assert_eq!(VoteMemo::parse(&text), Some(memo.clone()));
+      prop_assert_eq!(VoteMemo::parse(&text.to_uppercase().replace(' ', "  ")), Some(memo.clone()));
+      if text.len() <= MEMO_MAX_LENGTH {
+        prop_assert_eq!(decode_memo(&memo.encode().unwrap()).unwrap(), text);
+      }
+    }
+
+    #[test]
+    fn test_encode_memo_round_trips(text in "\\PC{0,32}") {
+      prop_assume!(text.len() <= MEMO_MAX_LENGTH);
+      prop_assert_eq!(decode_memo(&encode_memo(&text).unwrap()).unwrap(), text);
+    }
+
+    #[test]
+    fn test_parse_is_canonical(text in "\\PC{0,40}") {
+      if let Some(memo) = VoteMemo::parse(&text) {
+        prop_assert_eq!(VoteMemo::parse(&memo.to_string()), Some(memo));
+      }
+    }
+
+    #[test]
+    fn test_decode_memo_never_panics(memo in "[1-9A-HJ-NP-Za-km-z]{0,64}", bytes in prop::collection::vec(any::<u8>(), 0 .. 40)) {
+      let _ = decode_memo(&memo);
+      let _ = decode_memo(&bs58::encode(&bytes).with_check().into_string());
+    }
+
+    #[test]
+    fn test_tallying_never_panics(memos in prop::collection::vec(archive_memo(), 1 .. 8)) {
+      let votes: Vec<Vote> = memos
+        .iter()
+        .enumerate()
+        .map(|(i, memo)| Vote::new(format!("{}", i % 3), format!("{i}"), memo.clone(), i as i64, BlockStatus::Pending, i as i64, 0))
+        .collect();
+      let ranked: Vec<RankedVote> = votes
+        .iter()
+        .map(|vote| RankedVote::new(vote.account.clone(), vote.hash.clone(), vote.memo.clone(), vote.height, vote.status, vote.timestamp, vote.nonce))
+        .collect();
+
+      let _ = Wrapper(votes.clone()).process_with_rejections("cftest-2", 10, 1);
+      let _ = Wrapper(votes).process_mep_with_rejections(1, 1, 10, 1);
+      let _ = Wrapper(ranked).process_ranked_vote_with_rejections(1, 10, 1);
+    }
+  }
+}
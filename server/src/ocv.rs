@@ -1,48 +1,50 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, ensure};
 use rust_decimal::Decimal;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
 
 use crate::{
-  Archive, ElectionResult, ElectionStats, Ledger, Network, Proposal, RankedVote, ReleaseStage, Vote, VoteRules,
-  VoteWithWeight, Wrapper, ranked_vote::run_simple_election, util::Caches,
+  Archive, ArchiveInputs, ArchiveInterface, Attestation, ConsiderationChoice, ConsiderationTally, ElectionResult,
+  ElectionStats, FetchTransactionResult, FundingRound, LEDGER_BALANCE_SCALE, Ledger, LedgerDigest, LedgerSource,
+  LiveSnapshot, LiveTopic, MemoFilter, Network, Proposal, ProposalVersion, RankedVote, RejectedVote, RejectionReason,
+  ReleaseStage, StoredProposalResult, TallyRules, Vote, VoteIngestion, VoteMemo, VoteRules, VoteWithWeight,
+  VotingErrors, Wrapper, ranked_vote::run_weighted_election, util::Caches,
 };
 
 #[derive(Clone)]
-pub struct Ocv {
+pub struct Ocv<A = Archive> {
   pub caches: Caches,
-  pub archive: Archive,
+  pub archive: A,
   pub network: Network,
   pub release_stage: ReleaseStage,
   pub ledger_storage_path: PathBuf,
-  pub bucket_name: String,
+  pub results_storage_path: PathBuf,
+  pub ledger_source: LedgerSource,
   pub proposals: Vec<Proposal>,
+  pub rounds: Vec<FundingRound>,
+  /// Depth below the chain tip at which a block is considered final.
+  pub finality_depth: i64,
+  /// Whether ledgers published without a manifest are refused for weighting.
+  pub require_ledger_manifests: bool,
 }
 
-impl Ocv {
+impl<A: ArchiveInterface> Ocv<A> {
   pub async fn info(&self) -> Result<GetCoreApiInfoResponse> {
-    let chain_tip = self.archive.fetch_chain_tip()?;
-    let current_slot = self.archive.fetch_latest_slot()?;
+    let chain_tip = self.archive.fetch_chain_tip().await?;
+    let current_slot = self.archive.fetch_latest_slot().await?;
     Ok(GetCoreApiInfoResponse { chain_tip, current_slot })
   }
 
   pub async fn proposal(&self, id: usize) -> Result<ProposalResponse> {
     let proposal = self.find_proposal(id)?;
 
+    let (mut votes, _) = self.ingest_proposal_votes(&proposal).await?;
     if let Some(cached) = self.caches.votes.get(&proposal.key).await {
       return Ok(ProposalResponse { proposal, votes: cached.to_vec() });
     }
-
-    let transactions = self.archive.fetch_transactions(proposal.start_time, proposal.end_time)?;
-
-    let chain_tip = self.archive.fetch_chain_tip()?;
-
-    let votes = Wrapper(transactions.into_iter().map(std::convert::Into::into).collect())
-      .process(&proposal.key, chain_tip)
-      .sort_by_timestamp()
-      .to_vec()
-      .0;
+    let votes = votes.sort_by_timestamp().to_vec().0;
 
     self.caches.votes.insert(proposal.key.clone(), Arc::new(votes.clone())).await;
 
@@ -74,12 +76,17 @@ impl Ocv {
     total_positive_community_votes: usize,
     _total_negative_community_votes: usize,
   ) -> bool {
-    let min_positive_votes = if self.release_stage == ReleaseStage::Production { 10 } else { 2 };
+    let min_positive_votes = self.min_positive_votes();
     tracing::info!("min_positive_votes {}", min_positive_votes);
     tracing::info!("release_stage {}", self.release_stage);
     total_positive_community_votes >= min_positive_votes
   }
 
+  /// The positive community votes a MEF proposal needs to be considered.
+  pub fn min_positive_votes(&self) -> usize {
+    if self.release_stage == ReleaseStage::Production { 10 } else { 2 }
+  }
+
   pub async fn proposal_consideration(
     &self,
     round_id: usize,
@@ -88,116 +95,129 @@ impl Ocv {
     end_time: i64,
     ledger_hash: Option<String>,
   ) -> Result<GetMinaProposalConsiderationResponse> {
-    let key = format!("MEF_round_{}_proposal_{}_start_{}_end_{}", round_id, proposal_id, start_time, end_time);
-
+    let key = consideration_key(round_id, proposal_id, start_time, end_time);
+    let (mut ingested, inputs) = self.ingest_consideration_votes(round_id, proposal_id, start_time, end_time).await?;
     let votes = if let Some(cached_votes) = self.caches.votes.get(&key).await {
       cached_votes.to_vec()
     } else {
-      let transactions = self.archive.fetch_transactions(start_time, end_time)?;
-
-      let chain_tip = self.archive.fetch_chain_tip()?;
-      let votes = Wrapper(transactions.into_iter().map(std::convert::Into::into).collect())
-        .process_mep(round_id, proposal_id, chain_tip)
-        .sort_by_timestamp()
-        .to_vec()
-        .0;
+      let votes = ingested.sort_by_timestamp().to_vec().0;
 
       self.caches.votes.insert(key.clone(), Arc::new(votes.clone())).await;
       tracing::info!("votes {}", votes.len());
       votes
     };
-    // weighted votes
-    let mut positive_stake_weight = Decimal::from(0);
-    let mut negative_stake_weight = Decimal::from(0);
+    let rules = TallyRules::Consideration {
+      release_stage: self.release_stage,
+      min_positive_votes: self.min_positive_votes(),
+      finality_depth: self.finality_depth,
+    };
+    // Each vote is classified once; the headcount and the stake are tallied from
+    // the same classification.
+    let choices: Vec<ConsiderationChoice> =
+      votes.iter().map(|vote| ConsiderationChoice::classify(&vote.memo, round_id, proposal_id)).collect();
+    let headcount = ConsiderationTally::tally(choices.iter().map(|choice| (*choice, Decimal::ZERO)));
 
-    // check community votes
-    let mut total_positive_community_votes = 0;
-    let mut total_negative_community_votes = 0;
-    for vote in &votes {
-      if vote.memo.to_lowercase() == format!("mef{} yes {}", round_id, proposal_id) {
-        total_positive_community_votes += 1;
-      }
-      if vote.memo.to_lowercase() == format!("mef{} no {}", round_id, proposal_id) {
-        total_negative_community_votes += 1;
-      }
-    }
     // Check if enough positive votes
-    if !self.has_met_vote_threshold(total_positive_community_votes, total_negative_community_votes) {
+    if !self.has_met_vote_threshold(headcount.positive_votes, headcount.negative_votes) {
       return Ok(GetMinaProposalConsiderationResponse {
         round_id,
+        what_if: true,
         proposal_id,
         total_community_votes: votes.len(),
-        total_positive_community_votes,
-        total_negative_community_votes,
+        total_positive_community_votes: headcount.positive_votes,
+        total_negative_community_votes: headcount.negative_votes,
         total_stake_weight: Decimal::ZERO,
         positive_stake_weight: Decimal::ZERO,
         negative_stake_weight: Decimal::ZERO,
         elegible: false,
         vote_status: "Insufficient voters".to_string(),
         votes,
+        attestation: Attestation::new(None, None, inputs, rules),
       });
     }
 
     // Calculate weighted votes if ledger_hash params is provided
+    let mut ledger_digest = None;
+    let mut stakes = HashMap::new();
     if let Some(hash) = ledger_hash {
-      let votes_weighted = if let Some(cached_votes) = self.caches.votes_weighted.get(&key).await {
+      let ledger = self.ledger(&hash).await?;
+      ledger_digest = Some(LedgerDigest::new(&hash, &ledger));
+      let weighted_key = (key.clone(), hash.clone());
+      let votes_weighted = if let Some(cached_votes) = self.caches.votes_weighted.get(&weighted_key).await {
         cached_votes.to_vec()
       } else {
-        let transactions = self.archive.fetch_transactions(start_time, end_time)?;
-
-        let chain_tip = self.archive.fetch_chain_tip()?;
-
-        let ledger = if let Some(cached_ledger) = self.caches.ledger.get(&hash).await {
-          Ledger(cached_ledger.to_vec())
-        } else {
-          let ledger = Ledger::fetch(self, &hash).await?;
+        let votes = ingested.weighted_mep(&ledger).sort_by_timestamp().0;
 
-          self.caches.ledger.insert(hash, Arc::new(ledger.0.clone())).await;
-
-          ledger
-        };
-
-        let votes = Wrapper(transactions.into_iter().map(std::convert::Into::into).collect())
-          .into_weighted_mep(round_id, proposal_id, &ledger, chain_tip)
-          .sort_by_timestamp()
-          .0;
-
-        self.caches.votes_weighted.insert(key.clone(), Arc::new(votes.clone())).await;
+        self.caches.votes_weighted.insert(weighted_key, Arc::new(votes.clone())).await;
 
         votes
       };
-      for vote in &votes_weighted {
-        if vote.memo.to_lowercase() == format!("mef {} no {}", round_id, proposal_id) {
-          negative_stake_weight += vote.weight;
-        }
-        if vote.memo.to_lowercase() == format!("mef {} yes {}", round_id, proposal_id) {
-          positive_stake_weight += vote.weight;
-        }
-        positive_stake_weight += vote.weight;
-      }
+      stakes = votes_weighted.into_iter().map(|vote| (vote.account, vote.weight)).collect();
     } else {
       tracing::info!("ledger_hash is not provided.");
     }
 
-    let total_stake_weight = positive_stake_weight + negative_stake_weight;
+    let tally = ConsiderationTally::tally(
+      votes
+        .iter()
+        .zip(&choices)
+        .map(|(vote, choice)| (*choice, stakes.get(&vote.account).copied().unwrap_or(Decimal::ZERO))),
+    );
 
     // Voting results
     Ok(GetMinaProposalConsiderationResponse {
       round_id,
+      what_if: true,
       proposal_id,
       total_community_votes: votes.len(),
-      total_positive_community_votes,
-      total_negative_community_votes,
-      total_stake_weight,
-      positive_stake_weight,
-      negative_stake_weight,
+      total_positive_community_votes: tally.positive_votes,
+      total_negative_community_votes: tally.negative_votes,
+      total_stake_weight: tally.total_stake(),
+      positive_stake_weight: tally.positive_stake,
+      negative_stake_weight: tally.negative_stake,
       elegible: true,
       vote_status: "Proposal selected for the next phase".to_string(),
       votes,
+      attestation: Attestation::new(None, ledger_digest, inputs, rules),
     })
   }
 
+  /// The result of proposal `id`, served from `results_storage_path` once
+  /// final and stored there when it becomes final. A stored result computed
+  /// for a manifest entry that has since changed is computed again.
   pub async fn proposal_result(&self, id: usize) -> Result<GetMinaProposalResultResponse> {
+    let proposal = self.find_proposal(id)?;
+    if proposal.ledger_hash.is_some() {
+      match StoredProposalResult::load(self, &proposal)? {
+        Some(stored) if stored.is_for(&proposal) => return Ok(stored.result),
+        Some(stored) => tracing::warn!(
+          "stored result of proposal {} was computed with ledger {} for a different manifest entry, computing it again",
+          id,
+          stored.ledger_hash
+        ),
+        None => {}
+      }
+    }
+
+    let result = self.recompute_proposal_result(id).await?;
+    let (Some(hash), Some(attestation)) = (result.proposal.ledger_hash.clone(), &result.attestation) else {
+      return Ok(result);
+    };
+    let chain_tip = attestation.archive.chain_tip;
+    let final_block_timestamp = self.archive.fetch_block_timestamp(chain_tip - self.finality_depth).await?;
+    if StoredProposalResult::is_final(&result, final_block_timestamp) {
+      let stored = StoredProposalResult { ledger_hash: hash, chain_tip, result };
+      stored.save(self)?;
+      tracing::info!("stored final result of proposal {} at chain tip {}", id, chain_tip);
+      return Ok(stored.result);
+    }
+
+    Ok(result)
+  }
+
+  /// Computes the result of proposal `id` from the archive and the ledger,
+  /// neither reading nor writing stored results.
+  pub async fn recompute_proposal_result(&self, id: usize) -> Result<GetMinaProposalResultResponse> {
     let proposal = self.find_proposal(id)?;
     let hash = match proposal.ledger_hash.clone() {
       None => {
@@ -207,80 +227,121 @@ impl Ocv {
           positive_stake_weight: Decimal::ZERO,
           negative_stake_weight: Decimal::ZERO,
           votes: Vec::new(),
+          attestation: None,
         });
       }
       Some(value) => value,
     };
 
-    let votes = if let Some(cached_votes) = self.caches.votes_weighted.get(&proposal.key).await {
+    let ledger = self.ledger(&hash).await?;
+    let (ingested, inputs) = self.ingest_proposal_votes(&proposal).await?;
+    let weighted_key = (proposal.key.clone(), hash.clone());
+    let votes = if let Some(cached_votes) = self.caches.votes_weighted.get(&weighted_key).await {
       cached_votes.to_vec()
     } else {
-      let transactions = self.archive.fetch_transactions(proposal.start_time, proposal.end_time)?;
-
-      let chain_tip = self.archive.fetch_chain_tip()?;
-
-      let ledger = if let Some(cached_ledger) = self.caches.ledger.get(&hash).await {
-        Ledger(cached_ledger.to_vec())
-      } else {
-        let ledger = Ledger::fetch(self, &hash).await?;
+      let votes = ingested.weighted(&proposal.version, &ledger).sort_by_timestamp().0;
 
-        self.caches.ledger.insert(hash, Arc::new(ledger.0.clone())).await;
-
-        ledger
-      };
-
-      let votes = Wrapper(transactions.into_iter().map(std::convert::Into::into).collect())
-        .into_weighted(&proposal, &ledger, chain_tip)
-        .sort_by_timestamp()
-        .0;
-
-      self.caches.votes_weighted.insert(proposal.key.clone(), Arc::new(votes.clone())).await;
+      self.caches.votes_weighted.insert(weighted_key, Arc::new(votes.clone())).await;
 
       votes
     };
+    let rules = TallyRules::Proposal { version: proposal.version.clone(), finality_depth: self.finality_depth };
+    let attestation = Attestation::new(Some(proposal.clone()), Some(LedgerDigest::new(&hash, &ledger)), inputs, rules);
 
     let mut positive_stake_weight = Decimal::from(0);
     let mut negative_stake_weight = Decimal::from(0);
 
     for vote in &votes {
-      if vote.memo.split_whitespace().next().eq(&Some("no")) {
+      if matches!(VoteMemo::parse(&vote.memo), Some(VoteMemo::Binary { yes: false, .. })) {
         negative_stake_weight += vote.weight;
       } else {
         positive_stake_weight += vote.weight;
       }
     }
 
+    let total_stake_weight = positive_stake_weight + negative_stake_weight;
+    ensure!(
+      total_stake_weight <= ledger.total_currency(),
+      "Proposal {id} counts a stake of {total_stake_weight}, more than the {} in ledger {hash}.",
+      ledger.total_currency()
+    );
+
     Ok(GetMinaProposalResultResponse {
       proposal,
-      total_stake_weight: positive_stake_weight + negative_stake_weight,
+      total_stake_weight,
       positive_stake_weight,
       negative_stake_weight,
       votes,
+      attestation: Some(attestation),
     })
   }
 
+  /// What the result of proposal `id` was computed from.
+  pub async fn proposal_attestation(&self, id: usize) -> Result<Attestation> {
+    self.proposal_result(id).await?.attestation.ok_or(anyhow!("Proposal {id} has no ledger to be tallied with."))
+  }
+
+  /// The funding round `round_id` of the manifest.
+  pub fn round(&self, round_id: usize) -> Result<FundingRound> {
+    Ok(self.rounds.iter().find(|round| round.id == round_id).ok_or(anyhow!("Round {round_id} dne."))?.to_owned())
+  }
+
+  /// Funding round `round_id`, checking that `proposal_id` is one of its
+  /// candidates.
+  fn round_candidate(&self, round_id: usize, proposal_id: usize) -> Result<FundingRound> {
+    let round = self.round(round_id)?;
+    ensure!(round.proposals.contains(&proposal_id), "Proposal {proposal_id} is not a candidate of round {round_id}.");
+    Ok(round)
+  }
+
+  /// The consideration of candidate `proposal_id` of funding round
+  /// `round_id`, over the round's consideration window and ledger.
+  pub async fn round_consideration(
+    &self,
+    round_id: usize,
+    proposal_id: usize,
+  ) -> Result<GetMinaProposalConsiderationResponse> {
+    let round = self.round_candidate(round_id, proposal_id)?;
+    let window = round.consideration;
+    let mut response =
+      self.proposal_consideration(round_id, proposal_id, window.start_time, window.end_time, round.ledger_hash).await?;
+    response.what_if = false;
+    Ok(response)
+  }
+
+  /// The ranked vote of funding round `round_id`, over the round's ranking
+  /// window and ledger and among its candidate proposals.
+  pub async fn round_ranked_vote(&self, round_id: usize) -> Result<GetMinaRankedVoteResponse> {
+    let round = self.round(round_id)?;
+    let candidates: Vec<String> = round.proposals.iter().map(ToString::to_string).collect();
+    let window = round.ranking;
+    let mut response =
+      self.ranked_vote(round_id, window.start_time, window.end_time, round.ledger_hash, Some(&candidates)).await?;
+    response.what_if = false;
+    Ok(response)
+  }
+
+  /// Runs the ranked vote of round `round_id` over an arbitrary window, with
+  /// every proposal ranked by a voter as a candidate.
   pub async fn run_ranked_vote(
     &self,
     round_id: usize,
     start_time: i64,
     end_time: i64,
-    _ledger_hash: Option<String>,
+    ledger_hash: Option<String>,
   ) -> Result<GetMinaRankedVoteResponse> {
-    let transactions = self.archive.fetch_transactions(start_time, end_time)?;
-    let chain_tip = self.archive.fetch_chain_tip()?;
-    let key = format!("MEF_round_{}_start_{}_end_{}", round_id, start_time, end_time);
+    self.ranked_vote(round_id, start_time, end_time, ledger_hash, None).await
+  }
 
-    let votes = if let Some(cached_votes) = self.caches.ranked_votes.get(&key).await {
-      cached_votes.to_vec()
-    } else {
-      let votes = Wrapper(transactions.into_iter().map(std::convert::Into::into).collect())
-        .process_ranked_vote(round_id, chain_tip)
-        .to_vec()
-        .0;
-      self.caches.ranked_votes.insert(key.clone(), Arc::new(votes.clone())).await;
-      tracing::info!("votes {}", votes.len());
-      votes
-    };
+  async fn ranked_vote(
+    &self,
+    round_id: usize,
+    start_time: i64,
+    end_time: i64,
+    ledger_hash: Option<String>,
+    candidates: Option<&[String]>,
+  ) -> Result<GetMinaRankedVoteResponse> {
+    let (votes, inputs) = self.ingest_ranked_votes(round_id, start_time, end_time).await?;
     tracing::info!("run_ranked_vote {} {} {} {}", round_id, start_time, end_time, votes.len());
 
     let ranked_votes = votes; // Unwrap the wrapper to access the HashMap
@@ -290,28 +351,435 @@ impl Ocv {
       tracing::info!("vote_proposals {} {}", vote_proposals.len(), ranked_vote.account);
       votes.push(vote_proposals);
     }
-    let vote_rules = VoteRules::default();
-    let election = run_simple_election(&votes, &vote_rules);
-    let voting_result = match election {
-      Ok(result) => result, // If the election is successful, take the ElectionResult.
-      Err(error) => {
-        eprintln!("Election failed with error: {:?}", error);
-        ElectionResult {
-          winners: Some(vec![]), // Default to no winners
-          stats: vec![],         // Default to empty round statistics
-        }
-      }
-    };
+    let vote_rules = self.vote_rules(round_id);
+    let headcount: Vec<(Vec<&str>, u64)> = votes.iter().map(|choices| (choices.clone(), 1)).collect();
+    let voting_result = or_no_winners(run_weighted_election(&headcount, candidates, &vote_rules));
+
+    // With a ledger, the election is run again with each ballot weighted by
+    // the voter's stake in nanomina.
+    let mut ledger_digest = None;
+    let mut stake = None;
+    if let Some(hash) = ledger_hash {
+      let ledger = self.ledger(&hash).await?;
+      ledger_digest = Some(LedgerDigest::new(&hash, &ledger));
+      let ballots: Vec<(Vec<&str>, u64)> = ranked_votes
+        .iter()
+        .zip(&votes)
+        .filter_map(|(ranked_vote, choices)| {
+          let mut weight = ledger.get_stake_weight_mep(&ranked_vote.account).ok()?;
+          weight.rescale(LEDGER_BALANCE_SCALE);
+          let nanomina = u64::try_from(weight.mantissa()).ok().filter(|&nanomina| nanomina > 0)?;
+          Some((choices.clone(), nanomina))
+        })
+        .collect();
+      let stake_result = or_no_winners(run_weighted_election(&ballots, candidates, &vote_rules));
+      stake = Some(RankedVoteStakeTally {
+        total_stake: ballots.iter().map(|(_, nanomina)| nanomina).sum(),
+        total_votes: ballots.len(),
+        winners: stake_result.winners.unwrap_or_default(),
+        stats: stake_result.stats,
+      });
+    }
+    let rules = TallyRules::RankedVote { vote_rules: vote_rules.clone(), finality_depth: self.finality_depth };
 
     Ok(GetMinaRankedVoteResponse {
       round_id,
+      what_if: true,
+      vote_rules,
       total_votes: votes.len(),
-      winners: voting_result.winners.unwrap_or_else(Vec::new),
+      winners: voting_result.winners.unwrap_or_default(),
       stats: voting_result.stats,
+      stake,
       votes: ranked_votes,
+      attestation: Attestation::new(None, ledger_digest, inputs, rules),
     })
   }
 
+  /// How `public_key` voted on proposal `id`, and with what weight.
+  pub async fn proposal_voter(&self, id: usize, public_key: &str) -> Result<GetVoterResponse> {
+    let proposal = self.find_proposal(id)?;
+    let votes = self.proposal(id).await?.votes;
+    let superseded = self.proposal_rejections(id, Some(public_key)).await?;
+    let ledger = match &proposal.ledger_hash {
+      Some(hash) => Some(self.ledger(hash).await?),
+      None => None,
+    };
+    Ok(GetVoterResponse::new(public_key, votes, superseded, ledger.as_deref(), &proposal.version))
+  }
+
+  /// How `public_key` voted on a MEF proposal consideration, and with what
+  /// weight if a `ledger_hash` is given.
+  pub async fn consideration_voter(
+    &self,
+    round_id: usize,
+    proposal_id: usize,
+    start_time: i64,
+    end_time: i64,
+    ledger_hash: Option<String>,
+    public_key: &str,
+  ) -> Result<GetVoterResponse> {
+    let votes = self.proposal_consideration(round_id, proposal_id, start_time, end_time, None).await?.votes;
+    let superseded =
+      self.consideration_rejections(round_id, proposal_id, start_time, end_time, None, Some(public_key)).await?;
+    let ledger = match &ledger_hash {
+      Some(hash) => Some(self.ledger(hash).await?),
+      None => None,
+    };
+    // Considerations weight stake as V1 proposals do.
+    Ok(GetVoterResponse::new(public_key, votes, superseded, ledger.as_deref(), &ProposalVersion::V1))
+  }
+
+  /// Like [`Self::consideration_voter`], over the consideration window and
+  /// ledger of funding round `round_id`.
+  pub async fn round_consideration_voter(
+    &self,
+    round_id: usize,
+    proposal_id: usize,
+    public_key: &str,
+  ) -> Result<GetVoterResponse> {
+    let round = self.round_candidate(round_id, proposal_id)?;
+    let window = round.consideration;
+    self
+      .consideration_voter(round_id, proposal_id, window.start_time, window.end_time, round.ledger_hash, public_key)
+      .await
+  }
+
+  /// How `public_key` ranked the proposals of round `round_id`, and with what
+  /// weight if a `ledger_hash` is given.
+  pub async fn ranked_voter(
+    &self,
+    round_id: usize,
+    start_time: i64,
+    end_time: i64,
+    ledger_hash: Option<String>,
+    public_key: &str,
+  ) -> Result<GetRankedVoterResponse> {
+    let (votes, _) = self.ingest_ranked_votes(round_id, start_time, end_time).await?;
+    let vote = votes.into_iter().find(|vote| vote.account == public_key);
+    let superseded = self
+      .ranked_vote_rejections(round_id, start_time, end_time, Some(public_key))
+      .await?
+      .into_iter()
+      .filter(|r| matches!(r.reason, RejectionReason::SupersededBy { .. }))
+      .collect();
+    let weight = match (&vote, ledger_hash) {
+      (Some(_), Some(hash)) => self.ledger(&hash).await?.get_stake_weight_mep(public_key).unwrap_or_default(),
+      _ => Decimal::ZERO,
+    };
+    Ok(GetRankedVoterResponse { account: public_key.to_string(), vote, superseded, weight })
+  }
+
+  /// Like [`Self::ranked_voter`], over the ranking window and ledger of
+  /// funding round `round_id`.
+  pub async fn round_ranked_voter(&self, round_id: usize, public_key: &str) -> Result<GetRankedVoterResponse> {
+    let round = self.round(round_id)?;
+    let window = round.ranking;
+    self.ranked_voter(round_id, window.start_time, window.end_time, round.ledger_hash, public_key).await
+  }
+
+  /// The transactions of proposal `id` that were not counted, or counted
+  /// without weight, newest first. They are read from the same ingestion as
+  /// the votes served for the proposal. With an `account`, all of its
+  /// self-payments in the voting window are examined, including those whose
+  /// memo is not a vote on the proposal.
+  pub async fn proposal_rejections(&self, id: usize, account: Option<&str>) -> Result<Vec<RejectedVote>> {
+    let proposal = self.find_proposal(id)?;
+    if account.is_none() {
+      self.ingest_proposal_votes(&proposal).await?;
+    }
+    let (transactions, chain_tip) =
+      self.rejection_candidates(&proposal.key, proposal.start_time, proposal.end_time, account).await?;
+    let (votes, mut rejected) = Wrapper(transactions.into_iter().map(std::convert::Into::into).collect::<Vec<Vote>>())
+      .process_with_rejections(&proposal.key, chain_tip, self.finality_depth);
+    if let Some(hash) = &proposal.ledger_hash {
+      rejected.extend(votes.rejected_by_ledger(&proposal.version, &*self.ledger(hash).await?));
+    }
+    RejectedVote::sort(&mut rejected);
+    Ok(rejected)
+  }
+
+  /// Like [`Self::proposal_rejections`], for a MEF proposal consideration.
+  pub async fn consideration_rejections(
+    &self,
+    round_id: usize,
+    proposal_id: usize,
+    start_time: i64,
+    end_time: i64,
+    ledger_hash: Option<String>,
+    account: Option<&str>,
+  ) -> Result<Vec<RejectedVote>> {
+    if account.is_none() {
+      self.ingest_consideration_votes(round_id, proposal_id, start_time, end_time).await?;
+    }
+    let key = consideration_key(round_id, proposal_id, start_time, end_time);
+    let (transactions, chain_tip) = self.rejection_candidates(&key, start_time, end_time, account).await?;
+    let (votes, mut rejected) = Wrapper(transactions.into_iter().map(std::convert::Into::into).collect::<Vec<Vote>>())
+      .process_mep_with_rejections(round_id, proposal_id, chain_tip, self.finality_depth);
+    if let Some(hash) = ledger_hash {
+      rejected.extend(votes.rejected_by_ledger_mep(&*self.ledger(&hash).await?));
+    }
+    RejectedVote::sort(&mut rejected);
+    Ok(rejected)
+  }
+
+  /// Like [`Self::consideration_rejections`], over the consideration window
+  /// and ledger of funding round `round_id`.
+  pub async fn round_consideration_rejections(
+    &self,
+    round_id: usize,
+    proposal_id: usize,
+    account: Option<&str>,
+  ) -> Result<Vec<RejectedVote>> {
+    let round = self.round_candidate(round_id, proposal_id)?;
+    let window = round.consideration;
+    self
+      .consideration_rejections(round_id, proposal_id, window.start_time, window.end_time, round.ledger_hash, account)
+      .await
+  }
+
+  /// Like [`Self::ranked_vote_rejections`], over the ranking window of funding
+  /// round `round_id`.
+  pub async fn round_ranked_vote_rejections(
+    &self,
+    round_id: usize,
+    account: Option<&str>,
+  ) -> Result<Vec<RejectedVote>> {
+    let window = self.round(round_id)?.ranking;
+    self.ranked_vote_rejections(round_id, window.start_time, window.end_time, account).await
+  }
+
+  /// Like [`Self::proposal_rejections`], for a MEF ranked vote.
+  pub async fn ranked_vote_rejections(
+    &self,
+    round_id: usize,
+    start_time: i64,
+    end_time: i64,
+    account: Option<&str>,
+  ) -> Result<Vec<RejectedVote>> {
+    if account.is_none() {
+      self.ingest_ranked_votes(round_id, start_time, end_time).await?;
+    }
+    let key = ranked_vote_key(round_id, start_time, end_time);
+    let (transactions, chain_tip) = self.rejection_candidates(&key, start_time, end_time, account).await?;
+    let (_, mut rejected) =
+      Wrapper(transactions.into_iter().map(std::convert::Into::into).collect::<Vec<RankedVote>>())
+        .process_ranked_vote_with_rejections(round_id, chain_tip, self.finality_depth);
+    RejectedVote::sort(&mut rejected);
+    Ok(rejected)
+  }
+
+  /// The transactions to look for rejections in, with the chain tip they
+  /// were read at: every self-payment of `account` if given, otherwise the
+  /// transactions last ingested for `key`.
+  async fn rejection_candidates(
+    &self,
+    key: &str,
+    start_time: i64,
+    end_time: i64,
+    account: Option<&str>,
+  ) -> Result<(Vec<FetchTransactionResult>, i64)> {
+    if let Some(account) = account {
+      let transactions = self.archive.fetch_account_transactions(account, start_time, end_time).await?;
+      return Ok((transactions, self.archive.fetch_chain_tip().await?));
+    }
+    let not_ingested = || anyhow!("No votes have been ingested for {key}.");
+    let ingestion = self.caches.ingestion.get(key).await.ok_or_else(not_ingested)?;
+    let ingestion = ingestion.lock().await;
+    Ok((ingestion.transactions.clone(), ingestion.chain_tip.ok_or_else(not_ingested)?))
+  }
+
+  /// The indexed ledger for `hash`, fetched and indexed once and then served
+  /// from the cache.
+  async fn ledger(&self, hash: &String) -> Result<Arc<Ledger>> {
+    if let Some(cached_ledger) = self.caches.ledger.get(hash).await {
+      return Ok(cached_ledger);
+    }
+
+    let ledger = Arc::new(Ledger::fetch(self, hash).await?);
+
+    self.caches.ledger.insert(hash.clone(), ledger.clone()).await;
+
+    Ok(ledger)
+  }
+
+  /// Ingests the votes on a proposal of the manifest.
+  async fn ingest_proposal_votes(
+    &self,
+    proposal: &Proposal,
+  ) -> Result<(Wrapper<HashMap<String, Vote>>, ArchiveInputs)> {
+    let memo_filter = MemoFilter::Exact(vec![proposal.key.clone(), format!("no {}", proposal.key)]);
+    self
+      .ingest_votes(&proposal.key, proposal.start_time, proposal.end_time, &memo_filter, |votes, tip| {
+        votes.process(&proposal.key, tip, self.finality_depth)
+      })
+      .await
+  }
+
+  /// Ingests the consideration votes on MEF proposal `proposal_id` of round
+  /// `round_id`.
+  async fn ingest_consideration_votes(
+    &self,
+    round_id: usize,
+    proposal_id: usize,
+    start_time: i64,
+    end_time: i64,
+  ) -> Result<(Wrapper<HashMap<String, Vote>>, ArchiveInputs)> {
+    let key = consideration_key(round_id, proposal_id, start_time, end_time);
+    let memo_filter = MemoFilter::Exact(vec![
+      ConsiderationChoice::Yes.memo(round_id, proposal_id),
+      ConsiderationChoice::No.memo(round_id, proposal_id),
+    ]);
+    self
+      .ingest_votes(&key, start_time, end_time, &memo_filter, |votes, tip| {
+        votes.process_mep(round_id, proposal_id, tip, self.finality_depth)
+      })
+      .await
+  }
+
+  /// Reads the votes cast in a window, only fetching the blocks above the
+  /// last one that was final when the votes for `key` were last ingested.
+  ///
+  /// The votes cached for `key` are evicted whenever the read changed
+  /// anything, so that they are always derived from the returned votes.
+  async fn ingest_votes(
+    &self,
+    key: &str,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+    process: impl FnOnce(Wrapper<Vec<Vote>>, i64) -> Wrapper<HashMap<String, Vote>>,
+  ) -> Result<(Wrapper<HashMap<String, Vote>>, ArchiveInputs)> {
+    let ingestion =
+      self.caches.ingestion.get_with(key.to_string(), async { Arc::new(Mutex::new(VoteIngestion::default())) }).await;
+    let mut ingestion = ingestion.lock().await;
+
+    let (transactions, chain_tip, changed) =
+      self.read_ingestion(key, &mut ingestion, start_time, end_time, memo_filter).await?;
+    let delta = process(Wrapper(transactions.into_iter().map(std::convert::Into::into).collect()), chain_tip);
+    ingestion.merge(delta, chain_tip, self.finality_depth);
+    if changed {
+      self.caches.invalidate_key(key).await;
+    }
+    let inputs = ArchiveInputs::new(chain_tip, start_time, end_time, &ingestion.transactions);
+    Ok((Wrapper(ingestion.votes.clone()), inputs))
+  }
+
+  /// Like [`Self::ingest_votes`], for the ranked votes of round `round_id`.
+  /// They are processed again from every ingested transaction whenever the
+  /// read changed anything.
+  async fn ingest_ranked_votes(
+    &self,
+    round_id: usize,
+    start_time: i64,
+    end_time: i64,
+  ) -> Result<(Vec<RankedVote>, ArchiveInputs)> {
+    let key = &ranked_vote_key(round_id, start_time, end_time);
+    let ingestion =
+      self.caches.ingestion.get_with(key.to_string(), async { Arc::new(Mutex::new(VoteIngestion::default())) }).await;
+    let mut ingestion = ingestion.lock().await;
+
+    let memo_filter = MemoFilter::Prefix("mef".to_string());
+    let (_, chain_tip, changed) = self.read_ingestion(key, &mut ingestion, start_time, end_time, &memo_filter).await?;
+    ingestion.advance(chain_tip, self.finality_depth);
+    let inputs = ArchiveInputs::new(chain_tip, start_time, end_time, &ingestion.transactions);
+    if !changed {
+      if let Some(cached_votes) = self.caches.ranked_votes.get(key).await {
+        return Ok((cached_votes.to_vec(), inputs));
+      }
+    }
+    let votes = Wrapper(ingestion.transactions.iter().cloned().map(std::convert::Into::into).collect())
+      .process_ranked_vote(round_id, chain_tip, self.finality_depth)
+      .to_vec()
+      .0;
+    self.caches.ranked_votes.insert(key.to_string(), Arc::new(votes.clone())).await;
+    tracing::info!("votes {}", votes.len());
+    Ok((votes, inputs))
+  }
+
+  /// Reads the transactions of `ingestion` in the blocks above its cursor,
+  /// returning them with the chain tip and whether anything changed since
+  /// the last read.
+  ///
+  /// If a transaction in those blocks is no longer returned by the archive,
+  /// its block has been orphaned: the transactions are read again from
+  /// scratch, as a vote it superseded may have to be counted again.
+  async fn read_ingestion(
+    &self,
+    key: &str,
+    ingestion: &mut VoteIngestion,
+    start_time: i64,
+    end_time: i64,
+    memo_filter: &MemoFilter,
+  ) -> Result<(Vec<FetchTransactionResult>, i64, bool)> {
+    let mut read_above = ingestion.read_above();
+    let mut transactions = self.archive.fetch_transactions_above(read_above, start_time, end_time, memo_filter).await?;
+    let fetched_hashes = transactions.iter().map(|transaction| transaction.hash.clone()).collect();
+    if ingestion.has_orphaned_transactions(&fetched_hashes, read_above) {
+      tracing::warn!("orphaned votes detected for {}, ingesting again from {}", key, start_time);
+      *ingestion = VoteIngestion::default();
+      read_above = ingestion.read_above();
+      transactions = self.archive.fetch_transactions_above(read_above, start_time, end_time, memo_filter).await?;
+    }
+    let chain_tip = self.archive.fetch_chain_tip().await?;
+    tracing::info!("ingested {} transactions for {} above height {}", transactions.len(), key, read_above);
+
+    let changed = ingestion.record(read_above, &transactions, chain_tip);
+    Ok((transactions, chain_tip, changed))
+  }
+
+  /// A copy of this instance reading from `archive`. Only the ledger cache is
+  /// shared, as everything else in the caches was read from the archive.
+  pub fn with_archive<B: ArchiveInterface>(&self, archive: B) -> Ocv<B> {
+    Ocv {
+      caches: Caches { ledger: self.caches.ledger.clone(), ..Caches::build() },
+      archive,
+      network: self.network,
+      release_stage: self.release_stage,
+      ledger_storage_path: self.ledger_storage_path.clone(),
+      results_storage_path: self.results_storage_path.clone(),
+      ledger_source: self.ledger_source.clone(),
+      proposals: self.proposals.clone(),
+      finality_depth: self.finality_depth,
+      require_ledger_manifests: self.require_ledger_manifests,
+      rounds: self.rounds.clone(),
+    }
+  }
+
+  /// The votes and tally of a live topic, as of `chain_tip`.
+  pub async fn live_snapshot(&self, topic: LiveTopic, chain_tip: i64) -> Result<LiveSnapshot> {
+    let mut votes = Vec::new();
+    let tally = match topic {
+      LiveTopic::Proposal(id) => {
+        for vote in self.proposal(id).await?.votes {
+          votes.push((vote.hash.clone(), serde_json::to_value(vote)?));
+        }
+        serde_json::to_value(self.proposal_result(id).await?)?
+      }
+      LiveTopic::Round(round_id) => {
+        let mut considerations = Vec::new();
+        for proposal_id in self.round(round_id)?.proposals {
+          let consideration = self.round_consideration(round_id, proposal_id).await?;
+          for vote in &consideration.votes {
+            votes.push((vote.hash.clone(), serde_json::to_value(vote)?));
+          }
+          considerations.push(consideration);
+        }
+        let ranked_vote = self.round_ranked_vote(round_id).await?;
+        for vote in &ranked_vote.votes {
+          votes.push((vote.hash.clone(), serde_json::to_value(vote)?));
+        }
+        serde_json::json!({ "considerations": considerations, "ranked_vote": ranked_vote })
+      }
+    };
+    Ok(LiveSnapshot { chain_tip, votes, tally })
+  }
+
+  /// The rules of funding round `round_id`, or the default rules for a round
+  /// which is not in the manifest.
+  fn vote_rules(&self, round_id: usize) -> VoteRules {
+    self.rounds.iter().find(|round| round.id == round_id).map(|round| round.vote_rules.clone()).unwrap_or_default()
+  }
+
   fn find_proposal(&self, id: usize) -> Result<Proposal> {
     Ok(self.proposals.iter().find(|proposal| proposal.id == id).ok_or(anyhow!("Proposal {id} dne."))?.to_owned())
   }
@@ -330,19 +798,93 @@ pub struct ProposalResponse {
   votes: Vec<Vote>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct GetMinaProposalResultResponse {
   #[serde(flatten)]
-  proposal: Proposal,
-  total_stake_weight: Decimal,
-  positive_stake_weight: Decimal,
-  negative_stake_weight: Decimal,
-  votes: Vec<VoteWithWeight>,
+  pub proposal: Proposal,
+  pub total_stake_weight: Decimal,
+  pub positive_stake_weight: Decimal,
+  pub negative_stake_weight: Decimal,
+  pub votes: Vec<VoteWithWeight>,
+  /// What the result was computed from, if it was computed at all.
+  pub attestation: Option<Attestation>,
+}
+
+#[derive(Serialize)]
+pub struct GetVoterResponse {
+  account: String,
+  /// The vote counted for the account, if any.
+  vote: Option<Vote>,
+  /// The earlier votes of the account, replaced by the counted one.
+  superseded: Vec<RejectedVote>,
+  /// The account's entry in the staking ledger, if there is one to weight
+  /// votes with and the account is in it.
+  stake: Option<VoterStake>,
+  weight: Decimal,
+}
+
+#[derive(Serialize)]
+pub struct VoterStake {
+  balance: Decimal,
+  delegate: Option<String>,
+  /// The delegators whose stake is added to the account's weight.
+  delegators: Vec<VoterDelegator>,
+}
+
+#[derive(Serialize)]
+pub struct VoterDelegator {
+  account: String,
+  balance: Decimal,
+}
+
+impl GetVoterResponse {
+  fn new(
+    public_key: &str,
+    votes: Vec<Vote>,
+    rejected: Vec<RejectedVote>,
+    ledger: Option<&Ledger>,
+    version: &ProposalVersion,
+  ) -> Self {
+    let votes = Wrapper(votes.into_iter().map(|vote| (vote.account.clone(), vote)).collect::<HashMap<_, _>>());
+    let vote = votes.0.get(public_key).cloned();
+    let superseded =
+      rejected.into_iter().filter(|r| matches!(r.reason, RejectionReason::SupersededBy { .. })).collect();
+    let stake = ledger.and_then(|ledger| {
+      let account = ledger.account(public_key)?;
+      let delegators = ledger
+        .stake_delegators(&votes, version, public_key)
+        .into_iter()
+        .map(|(delegator, balance)| VoterDelegator { account: delegator.pk.clone(), balance })
+        .collect();
+      Some(VoterStake { balance: ledger.balance(public_key)?, delegate: account.delegate.clone(), delegators })
+    });
+    let weight = match (&vote, ledger) {
+      (Some(_), Some(ledger)) => ledger.get_stake_weight(&votes, version, public_key).unwrap_or_default(),
+      _ => Decimal::ZERO,
+    };
+    GetVoterResponse { account: public_key.to_string(), vote, superseded, stake, weight }
+  }
+}
+
+/// How an account ranked the proposals of a MEF round.
+#[derive(Serialize)]
+pub struct GetRankedVoterResponse {
+  account: String,
+  /// The ballot counted for the account, if any.
+  vote: Option<RankedVote>,
+  /// The earlier ballots of the account, replaced by the counted one.
+  superseded: Vec<RejectedVote>,
+  /// The stake the ballot carries in the stake-weighted election, see
+  /// [`RankedVoteStakeTally`].
+  weight: Decimal,
 }
 
 #[derive(Serialize)]
 pub struct GetMinaProposalConsiderationResponse {
   round_id: usize,
+  /// Whether the window and ledger were chosen by the caller rather than
+  /// taken from the round in the manifest.
+  what_if: bool,
   proposal_id: usize,
   total_community_votes: usize,
   total_positive_community_votes: usize,
@@ -353,13 +895,783 @@ pub struct GetMinaProposalConsiderationResponse {
   vote_status: String,
   elegible: bool,
   votes: Vec<Vote>,
+  attestation: Attestation,
+}
+
+/// The cache key of the consideration votes on a MEF proposal.
+fn consideration_key(round_id: usize, proposal_id: usize, start_time: i64, end_time: i64) -> String {
+  format!("MEF_round_{}_proposal_{}_start_{}_end_{}", round_id, proposal_id, start_time, end_time)
+}
+
+/// The cache key of the ranked votes of a MEF round.
+fn ranked_vote_key(round_id: usize, start_time: i64, end_time: i64) -> String {
+  format!("MEF_round_{}_start_{}_end_{}", round_id, start_time, end_time)
+}
+
+/// The results of an election fallen back to no winners, and no round
+/// statistics, if it failed.
+fn or_no_winners(election: Result<ElectionResult, VotingErrors>) -> ElectionResult {
+  election.unwrap_or_else(|error| {
+    tracing::error!("Election failed with error: {:?}", error);
+    ElectionResult { winners: Some(vec![]), stats: vec![] }
+  })
 }
 
 #[derive(Serialize)]
 pub struct GetMinaRankedVoteResponse {
   round_id: usize,
+  /// Whether the window and ledger were chosen by the caller rather than
+  /// taken from the round in the manifest.
+  what_if: bool,
+  /// The rules the elections were run with.
+  vote_rules: VoteRules,
+  /// The headcount election, where every account's ballot counts once.
   total_votes: usize,
   winners: Vec<String>,
   stats: Vec<ElectionStats>,
+  /// The stake-weighted election, if a ledger hash was given.
+  stake: Option<RankedVoteStakeTally>,
   votes: Vec<RankedVote>,
+  attestation: Attestation,
+}
+
+/// A ranked vote with every ballot weighted by the voter's stake.
+///
+/// Stake is given by [`Ledger::get_stake_weight_mep`]: delegated stake always
+/// goes to the delegate, and the ballots of accounts delegating elsewhere
+/// carry none. Tallies in the round statistics are in nanomina.
+#[derive(Serialize)]
+pub struct RankedVoteStakeTally {
+  /// The stake of all the ballots, in nanomina.
+  total_stake: u64,
+  /// The ballots with any stake.
+  total_votes: usize,
+  winners: Vec<String>,
+  stats: Vec<ElectionStats>,
+}
+
+#[cfg(test)]
+mod tests {
+  use proptest::prelude::*;
+
+  use super::*;
+  use crate::{
+    ArchiveBlock, ArchiveUserCommand, BlockStatus, LedgerAccount, LiveTallies, MemoryArchive, ProposalCategory,
+    VotingWindow, encode_memo,
+  };
+
+  const YES: &str = "E4YdLeukpqzqyBAxujeELx9SZWoUW9MhcUfnGHF9PhQmxTJcpmj7j"; // cftest-2
+  const NO: &str = "E4YjFkHVUXbEAkQcUrAEcS1fqvbncnn9Tuz2Jtb1Uu79zY9UAJRpd"; // no cftest-2
+  const OTHER: &str = "E4Yf92G48v8FApR4EWQq3iKb2vZkHHxZHPaZ73NQNBXmHeXNzHHSp"; // Payment#0
+  const MEF_YES: &str = "E4Yh4PzVLrCiugdoaASo5Ve6Do755ey6vGqkURC8z7qcADqMUcp9K"; // MEF1 YES 1
+  const MEF_NO: &str = "E4Yf7epFtpM8YAsxcGVagQQKmtUpwj8nKTWMQnWbXyhg7hE6ceJhJ"; // MEF1 NO 1
+  const RANKED_1: &str = "E4YkwtLx9t8gRCWWoc8cACHxKFSywt23uaGKTfmkwF1sNMh87FMEi"; // MEF 1 3 1 39
+  const RANKED_2: &str = "E4YntdXBbuz762QEcsaGicCkS86iZqzyKc8DUM9uWi1fBZ1JUeiDn"; // MEF 1 1 2 3 4
+
+  #[tokio::test]
+  async fn test_proposal() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", NO, 1))
+      .add_user_command(ArchiveUserCommand::vote(8, "A", "a2", YES, 2))
+      .add_user_command(ArchiveUserCommand::vote(15, "B", "b1", NO, 1))
+      .add_user_command(ArchiveUserCommand::vote(16, "C", "c1", OTHER, 1));
+    let ocv = get_ocv(archive);
+
+    let response = ocv.proposal(1).await.unwrap();
+    assert_eq!(response.votes.len(), 2);
+
+    let a = response.votes.iter().find(|v| v.account == "A").unwrap();
+    assert_eq!(a.hash, "a2");
+    assert_eq!(a.memo, "cftest-2");
+    assert_eq!(a.status, BlockStatus::Canonical);
+
+    let b = response.votes.iter().find(|v| v.account == "B").unwrap();
+    assert_eq!(b.memo, "no cftest-2");
+    assert_eq!(b.status, BlockStatus::Pending);
+
+    assert!(ocv.proposal(2).await.is_err());
+  }
+
+  #[tokio::test]
+  async fn test_proposal_ingests_incrementally() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(15, "B", "b1", NO, 1));
+    let ocv = get_ocv(archive.clone());
+
+    assert_eq!(ocv.proposal(1).await.unwrap().votes.len(), 2);
+    let ingestion = ocv.caches.ingestion.get("cftest-2").await.unwrap();
+    assert_eq!(ingestion.lock().await.cursor, Some(10));
+
+    // Blocks above the last final one are read again, including one that
+    // reaches the archive late with a timestamp older than the counted votes.
+    archive.add_block(ArchiveBlock { height: 21, global_slot: 21, timestamp: 4000, status: BlockStatus::Pending });
+    archive
+      .add_user_command(ArchiveUserCommand::vote(21, "C", "c1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(16, "B", "b2", YES, 2))
+      .add_user_command(ArchiveUserCommand::vote(17, "D", "d1", NO, 1));
+    ocv.caches.votes.invalidate("cftest-2").await;
+
+    let votes = ocv.proposal(1).await.unwrap().votes;
+    assert_eq!(votes.len(), 4);
+    assert_eq!(votes.iter().find(|v| v.account == "C").unwrap().hash, "c1");
+    assert_eq!(votes.iter().find(|v| v.account == "B").unwrap().hash, "b2");
+    assert_eq!(votes.iter().find(|v| v.account == "A").unwrap().status, BlockStatus::Canonical);
+    assert_eq!(ingestion.lock().await.cursor, Some(11));
+  }
+
+  #[tokio::test]
+  async fn test_proposal_evicts_orphaned_votes() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(18, "A", "a2", NO, 2))
+      .add_user_command(ArchiveUserCommand::vote(6, "B", "b1", NO, 1));
+    let ocv = get_ocv(archive.clone());
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+
+    let votes = ocv.proposal(1).await.unwrap().votes;
+    assert_eq!(votes.iter().find(|v| v.account == "A").unwrap().hash, "a2");
+    let result = ocv.proposal_result(1).await.unwrap();
+    assert_eq!(result.negative_stake_weight, Decimal::from(5));
+
+    // The votes still cached are checked against the archive all the same.
+    archive.set_block_status(18, BlockStatus::Orphaned);
+
+    let votes = ocv.proposal(1).await.unwrap().votes;
+    let a = votes.iter().find(|v| v.account == "A").unwrap();
+    assert_eq!(a.hash, "a1");
+    assert_eq!(a.memo, "cftest-2");
+
+    // The weighted result cached before the reorg has been evicted.
+    let result = ocv.proposal_result(1).await.unwrap();
+    assert_eq!(result.positive_stake_weight, Decimal::from(3));
+    assert_eq!(result.negative_stake_weight, Decimal::from(2));
+  }
+
+  #[tokio::test]
+  async fn test_proposal_finality_depth() {
+    let archive = get_archive();
+    archive.add_user_command(ArchiveUserCommand::vote(15, "A", "a1", YES, 1));
+    let mut ocv = get_ocv(archive);
+
+    assert_eq!(ocv.proposal(1).await.unwrap().votes[0].status, BlockStatus::Pending);
+
+    ocv.finality_depth = 5;
+    ocv.caches = Caches::build();
+    assert_eq!(ocv.proposal(1).await.unwrap().votes[0].status, BlockStatus::Canonical);
+  }
+
+  #[tokio::test]
+  async fn test_proposal_result() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(6, "B", "b1", NO, 1))
+      .add_user_command(ArchiveUserCommand::vote(7, "C", "c1", YES, 1));
+    let ocv = get_ocv(archive);
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+
+    let response = ocv.proposal_result(1).await.unwrap();
+    assert_eq!(response.votes.len(), 3);
+    assert_eq!(response.positive_stake_weight, Decimal::from(3));
+    assert_eq!(response.negative_stake_weight, Decimal::from(2));
+    assert_eq!(response.total_stake_weight, Decimal::from(5));
+  }
+
+  #[tokio::test]
+  async fn test_proposal_rejections() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(8, "A", "a2", NO, 2))
+      .add_user_command(ArchiveUserCommand::vote(9, "A", "a3", OTHER, 3))
+      .add_user_command(ArchiveUserCommand::vote(6, "C", "c1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(7, "F", "f1", YES, 1));
+    let ocv = get_ocv(archive);
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+
+    let rejected = ocv.proposal_rejections(1, Some("A")).await.unwrap();
+    let reasons: Vec<_> = rejected.into_iter().map(|r| (r.hash, r.reason)).collect();
+    assert_eq!(reasons, vec![
+      ("a3".to_string(), RejectionReason::WrongKey),
+      ("a1".to_string(), RejectionReason::SupersededBy { hash: "a2".to_string() }),
+    ]);
+
+    // Without an account, only transactions voting on the proposal are examined.
+    let rejected = ocv.proposal_rejections(1, None).await.unwrap();
+    let reasons: Vec<_> = rejected.into_iter().map(|r| (r.hash, r.reason)).collect();
+    assert_eq!(reasons, vec![
+      ("f1".to_string(), RejectionReason::NotInLedger),
+      ("a1".to_string(), RejectionReason::SupersededBy { hash: "a2".to_string() }),
+    ]);
+
+    let rejected = ocv.consideration_rejections(1, 1, 0, 100_000, Some("ledger".to_string()), Some("C")).await.unwrap();
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(rejected[0].memo, "cftest-2");
+    assert_eq!(rejected[0].reason, RejectionReason::WrongKey);
+  }
+
+  #[tokio::test]
+  async fn test_spaced_votes_are_counted_and_reported() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(6, "A", "a2", encode_memo("No  CFTest-2").unwrap(), 2))
+      .add_user_command(ArchiveUserCommand::vote(7, "B", "b1", encode_memo("cftest-2 ").unwrap(), 1));
+    let ocv = get_ocv(archive);
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+
+    let votes = ocv.proposal(1).await.unwrap().votes;
+    let mut hashes: Vec<&str> = votes.iter().map(|vote| vote.hash.as_str()).collect();
+    hashes.sort();
+    assert_eq!(hashes, vec!["a2", "b1"]);
+
+    let a = ocv.proposal_voter(1, "A").await.unwrap();
+    assert_eq!(a.vote.map(|vote| vote.hash), Some("a2".to_string()));
+    assert_eq!(a.superseded.iter().map(|r| r.hash.as_str()).collect::<Vec<_>>(), vec!["a1"]);
+    let b = ocv.proposal_voter(1, "B").await.unwrap();
+    assert_eq!(b.vote.map(|vote| vote.hash), Some("b1".to_string()));
+    assert!(b.superseded.is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_proposal_voter() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(8, "A", "a2", NO, 2))
+      .add_user_command(ArchiveUserCommand::vote(6, "C", "c1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(7, "B", "b1", MEF_YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(9, "C", "c2", MEF_NO, 2));
+    let ocv = get_ocv(archive);
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+
+    let a = ocv.proposal_voter(1, "A").await.unwrap();
+    assert_eq!(a.vote.map(|vote| vote.hash), Some("a2".to_string()));
+    assert_eq!(a.superseded.iter().map(|r| r.hash.as_str()).collect::<Vec<_>>(), vec!["a1"]);
+    let stake = a.stake.unwrap();
+    assert_eq!(stake.balance, Decimal::from(1));
+    // C voted itself, so only D's stake flows to A.
+    assert_eq!(stake.delegators.iter().map(|d| d.account.as_str()).collect::<Vec<_>>(), vec!["D"]);
+    assert_eq!(a.weight, Decimal::from(2));
+
+    let e = ocv.proposal_voter(1, "E").await.unwrap();
+    assert!(e.vote.is_none());
+    assert_eq!(e.stake.unwrap().delegate, Some("B".to_string()));
+    assert_eq!(e.weight, Decimal::ZERO);
+    assert!(ocv.proposal_voter(1, "F").await.unwrap().stake.is_none());
+
+    let c = ocv.consideration_voter(1, 1, 0, 100_000, Some("ledger".to_string()), "C").await.unwrap();
+    assert_eq!(c.vote.map(|vote| vote.hash), Some("c2".to_string()));
+    assert_eq!(c.weight, Decimal::ZERO);
+    let b = ocv.consideration_voter(1, 1, 0, 100_000, None, "B").await.unwrap();
+    assert!(b.stake.is_none());
+    assert_eq!(b.weight, Decimal::ZERO);
+  }
+
+  #[tokio::test]
+  async fn test_proposal_result_attestation() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(15, "B", "b1", NO, 1))
+      .add_user_command(ArchiveUserCommand::vote(16, "E", "e1", OTHER, 1));
+    let ocv = get_ocv(archive.clone());
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+
+    let attestation = ocv.proposal_attestation(1).await.unwrap();
+    assert_eq!(attestation.software_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(attestation.proposal.as_ref(), Some(&ocv.proposals[0]));
+    let ledger = attestation.ledger.unwrap();
+    assert_eq!(ledger.hash, "ledger");
+    assert_eq!(ledger.digest, get_ledger().accounts_digest());
+    assert_eq!(ledger.num_accounts, 5);
+    assert_eq!(attestation.archive.chain_tip, 20);
+    assert_eq!(attestation.archive.block_range, Some((5, 15)));
+    assert_eq!(attestation.archive.transaction_count, 2);
+    assert_eq!(attestation.rules, TallyRules::Proposal { version: ProposalVersion::V2, finality_depth: 10 });
+
+    // Only the window after A's final vote is read again, but the attestation
+    // still covers every transaction.
+    archive.add_user_command(ArchiveUserCommand::vote(17, "C", "c1", YES, 1));
+    let attestation = ocv.proposal_attestation(1).await.unwrap();
+    assert_eq!(attestation.archive.block_range, Some((5, 17)));
+    assert_eq!(attestation.archive.transaction_count, 3);
+    assert_eq!(ocv.proposal_attestation(1).await.unwrap(), attestation);
+  }
+
+  #[tokio::test]
+  async fn test_attestation_does_not_depend_on_ingestion_history() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(15, "B", "b1", NO, 1));
+    let warm = get_ocv(archive.clone());
+    warm.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+    warm.proposal_attestation(1).await.unwrap();
+
+    // The warm server keeps A's vote as read while its block was pending,
+    // where a cold one reads it as canonical.
+    for height in 1 ..= 20 {
+      archive.set_block_status(height, BlockStatus::Canonical);
+    }
+    let cold = get_ocv(archive);
+    cold.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+    assert_eq!(warm.proposal_attestation(1).await.unwrap(), cold.proposal_attestation(1).await.unwrap());
+  }
+
+  #[tokio::test]
+  async fn test_proposal_result_is_stored_once_final() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(15, "B", "b1", NO, 1));
+    let mut ocv = get_ocv(archive.clone());
+    ocv.proposals[0].end_time = 15_000;
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+
+    // B's vote is not final yet, so nothing is stored.
+    let result = ocv.proposal_result(1).await.unwrap();
+    assert_eq!(result.negative_stake_weight, Decimal::from(2));
+    assert!(StoredProposalResult::load(&ocv, &ocv.proposals[0]).unwrap().is_none());
+
+    for height in 21 ..= 30 {
+      archive.add_block(ArchiveBlock {
+        height,
+        global_slot: height * 2,
+        timestamp: height * 1000,
+        status: BlockStatus::Pending,
+      });
+    }
+    ocv.proposal_result(1).await.unwrap();
+    let stored = StoredProposalResult::load(&ocv, &ocv.proposals[0]).unwrap().unwrap();
+    assert_eq!(stored.ledger_hash, "ledger");
+    assert_eq!(stored.chain_tip, 30);
+
+    // Later changes to the archive or the caches no longer affect the result.
+    archive.add_user_command(ArchiveUserCommand::vote(12, "E", "e1", YES, 1));
+    ocv.caches = Caches::build();
+    let result = ocv.proposal_result(1).await.unwrap();
+    assert_eq!(result.negative_stake_weight, Decimal::from(2));
+    assert_eq!(result.votes.len(), 2);
+
+    // Unless the result is recomputed, which leaves the stored one as is.
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+    let recomputed = ocv.recompute_proposal_result(1).await.unwrap();
+    assert_eq!(recomputed.votes.len(), 3);
+    assert_eq!(recomputed.negative_stake_weight, Decimal::from(1));
+    let stored = StoredProposalResult::load(&ocv, &ocv.proposals[0]).unwrap().unwrap();
+    assert_eq!(stored.result.negative_stake_weight, Decimal::from(2));
+
+    // Once the manifest entry is corrected, the stored result is stale and is
+    // replaced.
+    ocv.proposals[0].version = ProposalVersion::V1;
+    let result = ocv.proposal_result(1).await.unwrap();
+    assert_eq!(result.proposal, ocv.proposals[0]);
+    assert_eq!(result.votes.len(), 3);
+    let stored = StoredProposalResult::load(&ocv, &ocv.proposals[0]).unwrap().unwrap();
+    assert!(stored.is_for(&ocv.proposals[0]));
+
+    ocv.proposals[0].ledger_hash = Some("corrected".to_string());
+    ocv.caches.ledger.insert("corrected".to_string(), Arc::new(get_ledger())).await;
+    let stored = StoredProposalResult::load(&ocv, &ocv.proposals[0]).unwrap().unwrap();
+    assert!(!stored.is_for(&ocv.proposals[0]));
+    ocv.proposal_result(1).await.unwrap();
+    let stored = StoredProposalResult::load(&ocv, &ocv.proposals[0]).unwrap().unwrap();
+    assert_eq!(stored.ledger_hash, "corrected");
+  }
+
+  #[tokio::test]
+  async fn test_proposal_result_is_not_stored_before_the_archive_passes_end_time() {
+    let archive = get_archive();
+    archive.add_user_command(ArchiveUserCommand::vote(5, "A", "a1", YES, 1));
+    let ocv = get_ocv(archive.clone());
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+
+    // The window closed long ago by the wall clock and A's vote is final, but
+    // the archive's last final block is still inside the window.
+    let result = ocv.proposal_result(1).await.unwrap();
+    assert_eq!(result.votes[0].status, BlockStatus::Canonical);
+    assert!(StoredProposalResult::load(&ocv, &ocv.proposals[0]).unwrap().is_none());
+
+    archive.add_block(ArchiveBlock { height: 21, global_slot: 21, timestamp: 200_000, status: BlockStatus::Pending });
+    archive.add_block(ArchiveBlock { height: 31, global_slot: 31, timestamp: 210_000, status: BlockStatus::Pending });
+    ocv.proposal_result(1).await.unwrap();
+    assert!(StoredProposalResult::load(&ocv, &ocv.proposals[0]).unwrap().is_some());
+  }
+
+  #[tokio::test]
+  async fn test_proposal_consideration() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", MEF_YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(6, "B", "b1", MEF_NO, 1));
+    let ocv = get_ocv(archive.clone());
+
+    let response = ocv.proposal_consideration(1, 1, 0, 100_000, None).await.unwrap();
+    assert_eq!(response.total_community_votes, 2);
+    assert_eq!(response.total_positive_community_votes, 1);
+    assert_eq!(response.total_negative_community_votes, 1);
+    assert!(!response.elegible);
+
+    archive.add_user_command(ArchiveUserCommand::vote(7, "C", "c1", MEF_YES, 1));
+    let response = ocv.proposal_consideration(1, 1, 0, 50_000, None).await.unwrap();
+    assert_eq!(response.total_positive_community_votes, 2);
+    assert!(response.elegible);
+  }
+
+  #[tokio::test]
+  async fn test_proposal_consideration_stake() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", MEF_YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(6, "B", "b1", MEF_NO, 1))
+      .add_user_command(ArchiveUserCommand::vote(7, "F", "f1", MEF_YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(8, "G", "g1", OTHER, 1));
+    let ocv = get_ocv(archive);
+    ocv.caches.ledger.insert("consideration".to_string(), Arc::new(get_ledger())).await;
+
+    // F is not in the ledger, so its vote counts towards the headcount only.
+    let response = ocv.proposal_consideration(1, 1, 0, 100_000, Some("consideration".to_string())).await.unwrap();
+    assert!(response.elegible);
+    assert_eq!(response.total_community_votes, 3);
+    assert_eq!(response.total_positive_community_votes, 2);
+    assert_eq!(response.total_negative_community_votes, 1);
+    assert_eq!(response.positive_stake_weight, Decimal::from(3));
+    assert_eq!(response.negative_stake_weight, Decimal::from(2));
+    assert_eq!(response.total_stake_weight, Decimal::from(5));
+
+    // The same window weighted with another ledger is not served the weights
+    // cached for the first.
+    let other = Ledger::new(vec![
+      LedgerAccount::new("A".to_string(), "10".to_string(), None),
+      LedgerAccount::new("B".to_string(), "1".to_string(), None),
+    ]);
+    ocv.caches.ledger.insert("other".to_string(), Arc::new(other)).await;
+    let response = ocv.proposal_consideration(1, 1, 0, 100_000, Some("other".to_string())).await.unwrap();
+    assert_eq!(response.positive_stake_weight, Decimal::from(10));
+    assert_eq!(response.negative_stake_weight, Decimal::from(1));
+    let response = ocv.proposal_consideration(1, 1, 0, 100_000, Some("consideration".to_string())).await.unwrap();
+    assert_eq!(response.positive_stake_weight, Decimal::from(3));
+  }
+
+  #[tokio::test]
+  async fn test_run_ranked_vote() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", RANKED_1, 1))
+      .add_user_command(ArchiveUserCommand::vote(6, "B", "b1", RANKED_1, 1))
+      .add_user_command(ArchiveUserCommand::vote(7, "C", "c1", RANKED_2, 1))
+      .add_user_command(ArchiveUserCommand::vote(8, "D", "d1", YES, 1));
+    let ocv = get_ocv(archive);
+
+    let response = ocv.run_ranked_vote(1, 0, 100_000, None).await.unwrap();
+    assert_eq!(response.total_votes, 3);
+    assert_eq!(response.winners.first().map(String::as_str), Some("3"));
+    assert!(response.stake.is_none());
+    assert_eq!(response.vote_rules, VoteRules::default());
+  }
+
+  #[tokio::test]
+  async fn test_ranked_vote_evicts_orphaned_votes() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", RANKED_1, 1))
+      .add_user_command(ArchiveUserCommand::vote(18, "A", "a2", RANKED_2, 2))
+      .add_user_command(ArchiveUserCommand::vote(19, "C", "c1", RANKED_2, 1));
+    let ocv = get_ocv(archive.clone());
+
+    let response = ocv.run_ranked_vote(1, 0, 100_000, None).await.unwrap();
+    assert_eq!(response.total_votes, 2);
+    assert_eq!(response.votes.iter().find(|v| v.account == "A").unwrap().hash, "a2");
+
+    archive.set_block_status(18, BlockStatus::Orphaned);
+
+    let response = ocv.run_ranked_vote(1, 0, 100_000, None).await.unwrap();
+    assert_eq!(response.total_votes, 2);
+    assert_eq!(response.votes.iter().find(|v| v.account == "A").unwrap().hash, "a1");
+    assert_eq!(response.attestation.archive.transaction_count, 2);
+  }
+
+  #[tokio::test]
+  async fn test_run_ranked_vote_with_round_rules() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", RANKED_1, 1))
+      .add_user_command(ArchiveUserCommand::vote(6, "B", "b1", RANKED_2, 1));
+    let mut ocv = get_ocv(archive);
+    let vote_rules = VoteRules { max_rankings_allowed: Some(1), ..VoteRules::default() };
+    ocv.rounds = vec![FundingRound { vote_rules: vote_rules.clone(), ..get_round() }];
+
+    let response = ocv.run_ranked_vote(1, 0, 100_000, None).await.unwrap();
+    assert_eq!(response.vote_rules, vote_rules);
+    assert_eq!(response.winners.len(), 1);
+    assert_eq!(response.attestation.rules, TallyRules::RankedVote { vote_rules, finality_depth: 10 });
+  }
+
+  #[tokio::test]
+  async fn test_round_endpoints() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(2, "A", "a1", MEF_YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(3, "B", "b1", MEF_YES, 1))
+      .add_user_command(ArchiveUserCommand::vote(9, "C", "c1", MEF_NO, 1))
+      .add_user_command(ArchiveUserCommand::vote(11, "A", "a0", RANKED_2, 1))
+      .add_user_command(ArchiveUserCommand::vote(12, "A", "a2", RANKED_1, 2))
+      .add_user_command(ArchiveUserCommand::vote(13, "B", "b2", RANKED_2, 2));
+    let mut ocv = get_ocv(archive);
+    ocv.rounds = vec![get_round()];
+
+    // Only the votes within the round's consideration window count.
+    let consideration = ocv.round_consideration(1, 1).await.unwrap();
+    assert!(!consideration.what_if);
+    assert_eq!(consideration.total_community_votes, 2);
+    assert!(ocv.proposal_consideration(1, 1, 0, 100_000, None).await.unwrap().what_if);
+    assert!(ocv.round_consideration(1, 2).await.is_err());
+    assert!(ocv.round_consideration(2, 1).await.is_err());
+
+    // Proposals ranked which are not candidates of the round are skipped.
+    let ranked = ocv.round_ranked_vote(1).await.unwrap();
+    assert!(!ranked.what_if);
+    assert_eq!(ranked.total_votes, 2);
+    assert_eq!(ranked.winners, vec!["1", "3"]);
+    let what_if = ocv.run_ranked_vote(1, 0, 100_000, None).await.unwrap();
+    assert!(what_if.what_if);
+    assert!(what_if.winners.contains(&"39".to_string()));
+
+    // Voters can check themselves against the official round.
+    let voter = ocv.round_consideration_voter(1, 1, "A").await.unwrap();
+    assert_eq!(voter.vote.map(|vote| vote.hash), Some("a1".to_string()));
+    assert!(ocv.round_consideration_voter(1, 1, "C").await.unwrap().vote.is_none());
+    assert!(ocv.round_consideration_voter(1, 2, "A").await.is_err());
+    assert!(ocv.round_consideration_rejections(1, 1, None).await.unwrap().is_empty());
+    let ranked_voter = ocv.round_ranked_voter(1, "A").await.unwrap();
+    assert_eq!(ranked_voter.vote.map(|vote| vote.hash), Some("a2".to_string()));
+    assert_eq!(ranked_voter.superseded.iter().map(|r| r.hash.as_str()).collect::<Vec<_>>(), vec!["a0"]);
+    assert_eq!(ranked_voter.weight, Decimal::ZERO);
+    let rejections = ocv.round_ranked_vote_rejections(1, Some("A")).await.unwrap();
+    assert_eq!(rejections.iter().map(|r| r.hash.as_str()).collect::<Vec<_>>(), vec!["a0"]);
+  }
+
+  fn get_round() -> FundingRound {
+    FundingRound {
+      id: 1,
+      network: Network::Devnet,
+      consideration: VotingWindow { start_time: 0, end_time: 5000 },
+      ranking: VotingWindow { start_time: 10_000, end_time: 15_000 },
+      ledger_hash: None,
+      proposals: vec![1, 3],
+      vote_rules: VoteRules::default(),
+    }
+  }
+
+  #[tokio::test]
+  async fn test_run_stake_weighted_ranked_vote() {
+    let archive = get_archive();
+    archive
+      .add_user_command(ArchiveUserCommand::vote(5, "A", "a1", RANKED_1, 1))
+      .add_user_command(ArchiveUserCommand::vote(6, "B", "b1", RANKED_1, 1))
+      .add_user_command(ArchiveUserCommand::vote(7, "C", "c1", RANKED_1, 1))
+      .add_user_command(ArchiveUserCommand::vote(8, "E", "e1", RANKED_2, 1));
+    let ocv = get_ocv(archive);
+    let ledger = Ledger::new(vec![
+      LedgerAccount::new("A".to_string(), "1".to_string(), None),
+      LedgerAccount::new("B".to_string(), "1".to_string(), None),
+      LedgerAccount::new("C".to_string(), "1".to_string(), Some("A".to_string())),
+      LedgerAccount::new("E".to_string(), "10".to_string(), None),
+    ]);
+    ocv.caches.ledger.insert("ranked".to_string(), Arc::new(ledger)).await;
+
+    let response = ocv.run_ranked_vote(1, 0, 100_000, Some("ranked".to_string())).await.unwrap();
+    assert_eq!(response.total_votes, 4);
+    assert_eq!(response.winners.first().map(String::as_str), Some("3"));
+
+    // C delegates to A, so its ballot has no stake of its own.
+    let stake = response.stake.unwrap();
+    assert_eq!(stake.total_votes, 3);
+    assert_eq!(stake.total_stake, 13_000_000_000);
+    assert_eq!(stake.winners.first().map(String::as_str), Some("1"));
+    assert_eq!(response.attestation.ledger.map(|ledger| ledger.hash), Some("ranked".to_string()));
+  }
+
+  #[tokio::test]
+  async fn test_live_tallies() {
+    let archive = get_archive();
+    // Not final yet, so the result is not stored and is tallied again.
+    archive.add_user_command(ArchiveUserCommand::vote(15, "A", "a1", YES, 1));
+    let ocv = get_ocv(archive.clone());
+    ocv.caches.ledger.insert("ledger".to_string(), Arc::new(get_ledger())).await;
+    let live = LiveTallies::spawn(Arc::new(ocv), std::time::Duration::from_millis(10));
+    assert!(live.subscribe(LiveTopic::Proposal(2)).is_err());
+    assert!(live.subscribe(LiveTopic::Round(1)).is_err());
+    let mut receiver = live.subscribe(LiveTopic::Proposal(1)).unwrap();
+
+    let snapshot = next_snapshot(&mut receiver).await;
+    assert_eq!(snapshot.chain_tip, 20);
+    assert_eq!(snapshot.votes.iter().map(|(hash, _)| hash.as_str()).collect::<Vec<_>>(), ["a1"]);
+    let stake = |snapshot: &LiveSnapshot, field: &str| -> Decimal {
+      serde_json::from_value(snapshot.tally[field].clone()).unwrap()
+    };
+    assert_eq!(stake(&snapshot, "positive_stake_weight"), Decimal::from(3));
+
+    // A second subscriber shares the snapshot rather than tallying again.
+    let mut other = live.subscribe(LiveTopic::Proposal(1)).unwrap();
+    assert_eq!(next_snapshot(&mut other).await, snapshot);
+
+    archive.add_user_command(ArchiveUserCommand::vote(21, "B", "b1", NO, 1)).add_block(ArchiveBlock {
+      height: 21,
+      global_slot: 42,
+      timestamp: 21_000,
+      status: BlockStatus::Pending,
+    });
+    let snapshot = next_snapshot(&mut receiver).await;
+    assert_eq!(snapshot.chain_tip, 21);
+    assert_eq!(snapshot.votes.len(), 2);
+    assert_eq!(stake(&snapshot, "negative_stake_weight"), Decimal::from(2));
+    assert_eq!(next_snapshot(&mut other).await, snapshot);
+  }
+
+  async fn next_snapshot(receiver: &mut tokio::sync::watch::Receiver<Option<Arc<LiveSnapshot>>>) -> Arc<LiveSnapshot> {
+    loop {
+      tokio::time::timeout(std::time::Duration::from_secs(5), receiver.changed()).await.unwrap().unwrap();
+      if let Some(snapshot) = receiver.borrow_and_update().clone() {
+        return snapshot;
+      }
+    }
+  }
+
+  const ACCOUNTS: [&str; 6] = ["A", "B", "C", "D", "E", "F"];
+
+  /// A staking ledger over some of [`ACCOUNTS`], each with a balance in
+  /// nanomina and possibly a delegate among them.
+  fn ledger_accounts() -> impl Strategy<Value = Vec<LedgerAccount>> {
+    prop::collection::vec(prop::option::of((0u64 .. 1_000_000_000_000, prop::option::of(0 .. ACCOUNTS.len()))), 6)
+      .prop_map(|entries| {
+        entries
+          .into_iter()
+          .zip(ACCOUNTS)
+          .filter_map(|(entry, pk)| {
+            let (nanomina, delegate) = entry?;
+            let balance = Decimal::new(nanomina as i64, 9).to_string();
+            Some(LedgerAccount::new(pk.to_string(), balance, delegate.map(|i| ACCOUNTS[i].to_string())))
+          })
+          .collect()
+      })
+  }
+
+  /// Self-payments by [`ACCOUNTS`] as (account, height, memo), with memos
+  /// voting either way on MEF proposal 1 of round 1, on another proposal, or
+  /// not voting at all.
+  fn consideration_votes() -> impl Strategy<Value = Vec<(usize, i64, String)>> {
+    let memos = vec![
+      MEF_YES.to_string(),
+      MEF_NO.to_string(),
+      OTHER.to_string(),
+      encode_memo("mef1 no 1").unwrap(),
+      encode_memo("MEF1 YES 2").unwrap(),
+    ];
+    prop::collection::vec((0 .. ACCOUNTS.len(), 1i64 ..= 20, prop::sample::select(memos)), 0 .. 30)
+  }
+
+  proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_consideration_conserves_stake(accounts in ledger_accounts(), votes in consideration_votes()) {
+      let archive = get_archive();
+      for (i, (account, height, memo)) in votes.iter().enumerate() {
+        archive.add_user_command(ArchiveUserCommand::vote(*height, ACCOUNTS[*account], format!("tx{i}"), memo, i as i64));
+      }
+      let ledger = Ledger::new(accounts);
+      let ocv = get_ocv(archive);
+      let response = tokio::runtime::Runtime::new().unwrap().block_on(async {
+        ocv.caches.ledger.insert("generated".to_string(), Arc::new(ledger.clone())).await;
+        ocv.proposal_consideration(1, 1, 0, 100_000, Some("generated".to_string())).await.unwrap()
+      });
+
+      let (mut positive_votes, mut negative_votes) = (0, 0);
+      let (mut positive_stake, mut negative_stake) = (Decimal::ZERO, Decimal::ZERO);
+      for vote in &response.votes {
+        let weight = ledger.get_stake_weight_mep(&vote.account).unwrap_or(Decimal::ZERO);
+        match ConsiderationChoice::classify(&vote.memo, 1, 1) {
+          ConsiderationChoice::Yes => (positive_votes, positive_stake) = (positive_votes + 1, positive_stake + weight),
+          ConsiderationChoice::No => (negative_votes, negative_stake) = (negative_votes + 1, negative_stake + weight),
+          ConsiderationChoice::Invalid => {}
+        }
+      }
+      prop_assert_eq!(response.total_community_votes, response.votes.len());
+      prop_assert_eq!(response.total_positive_community_votes, positive_votes);
+      prop_assert_eq!(response.total_negative_community_votes, negative_votes);
+      if response.elegible {
+        prop_assert_eq!(response.positive_stake_weight, positive_stake);
+        prop_assert_eq!(response.negative_stake_weight, negative_stake);
+      } else {
+        prop_assert_eq!(response.positive_stake_weight + response.negative_stake_weight, Decimal::ZERO);
+      }
+      prop_assert_eq!(response.total_stake_weight, response.positive_stake_weight + response.negative_stake_weight);
+    }
+  }
+
+  fn get_archive() -> MemoryArchive {
+    let archive = MemoryArchive::new();
+    for height in 1 ..= 20 {
+      archive.add_block(ArchiveBlock {
+        height,
+        global_slot: height * 2,
+        timestamp: height * 1000,
+        status: BlockStatus::Pending,
+      });
+    }
+    archive
+  }
+
+  fn get_ledger() -> Ledger {
+    Ledger::new(vec![
+      LedgerAccount::new("A".to_string(), "1".to_string(), None),
+      LedgerAccount::new("B".to_string(), "1".to_string(), None),
+      LedgerAccount::new("C".to_string(), "1".to_string(), Some("A".to_string())),
+      LedgerAccount::new("D".to_string(), "1".to_string(), Some("A".to_string())),
+      LedgerAccount::new("E".to_string(), "1".to_string(), Some("B".to_string())),
+    ])
+  }
+
+  fn get_results_dir() -> std::path::PathBuf {
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("mina-ocv-results-{}-{}", std::process::id(), n));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  fn get_ocv(archive: MemoryArchive) -> Ocv<MemoryArchive> {
+    Ocv {
+      caches: Caches::build(),
+      archive,
+      network: Network::Devnet,
+      release_stage: ReleaseStage::Development,
+      ledger_storage_path: std::env::temp_dir(),
+      results_storage_path: get_results_dir(),
+      ledger_source: LedgerSource::Directory(std::env::temp_dir()),
+      finality_depth: 10,
+      require_ledger_manifests: false,
+      rounds: Vec::new(),
+      proposals: vec![Proposal {
+        id: 1,
+        key: "cftest-2".to_string(),
+        start_time: 0,
+        end_time: 100_000,
+        epoch: 1,
+        ledger_hash: Some("ledger".to_string()),
+        category: ProposalCategory::Core,
+        version: ProposalVersion::V2,
+        title: "Test".to_string(),
+        description: "Test".to_string(),
+        url: String::new(),
+        network: Network::Devnet,
+      }],
+    }
+  }
 }
@@ -1,13 +1,73 @@
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
 
-use crate::Network;
+use crate::{Network, VoteMemo, VoteRules, memo::MEMO_MAX_LENGTH};
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ProposalsManifest {
   pub proposals: Vec<Proposal>,
+  /// The MEF funding rounds, voted on with ranked choices.
+  #[serde(default)]
+  pub rounds: Vec<FundingRound>,
+}
+
+impl ProposalsManifest {
+  /// Checks the manifest for proposals which could not be tallied as
+  /// intended, returning a description of each problem found.
+  pub fn validate(&self) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut ids = HashSet::new();
+    let mut keys = HashSet::new();
+    for proposal in &self.proposals {
+      let id = proposal.id;
+      if !ids.insert(id) {
+        problems.push(format!("proposal {id}: duplicate id"));
+      }
+      if proposal.key.trim().is_empty() {
+        problems.push(format!("proposal {id}: empty key"));
+      } else if !keys.insert((proposal.network, proposal.key.to_lowercase())) {
+        problems.push(format!("proposal {id}: duplicate key {} on {}", proposal.key, proposal.network));
+      }
+      let is_vote_on_key = VoteMemo::parse(&proposal.key).is_some_and(|memo| memo.is_binary_vote_on(&proposal.key));
+      if !proposal.key.trim().is_empty() && !is_vote_on_key {
+        problems.push(format!("proposal {id}: key {} is not read as a vote on it", proposal.key));
+      }
+      if format!("no {}", proposal.key).len() > MEMO_MAX_LENGTH {
+        problems.push(format!("proposal {id}: key {} does not fit in a memo", proposal.key));
+      }
+      if proposal.start_time >= proposal.end_time {
+        problems.push(format!("proposal {id}: start_time is not before end_time"));
+      }
+      if proposal.ledger_hash.as_ref().is_some_and(|hash| hash.trim().is_empty()) {
+        problems.push(format!("proposal {id}: empty ledger_hash"));
+      }
+    }
+    let mut round_ids = HashSet::new();
+    for round in &self.rounds {
+      let id = round.id;
+      if !round_ids.insert((round.network, id)) {
+        problems.push(format!("round {id}: duplicate id on {}", round.network));
+      }
+      for (name, window) in [("consideration", round.consideration), ("ranking", round.ranking)] {
+        if window.start_time >= window.end_time {
+          problems.push(format!("round {id}: {name} start_time is not before end_time"));
+        }
+      }
+      if round.proposals.is_empty() {
+        problems.push(format!("round {id}: no candidate proposals"));
+      } else if round.proposals.iter().collect::<HashSet<_>>().len() != round.proposals.len() {
+        problems.push(format!("round {id}: duplicate candidate proposals"));
+      }
+      if round.ledger_hash.as_ref().is_some_and(|hash| hash.trim().is_empty()) {
+        problems.push(format!("round {id}: empty ledger_hash"));
+      }
+    }
+    problems
+  }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Proposal {
   pub id: usize,
   pub key: String,
@@ -23,7 +83,7 @@ pub struct Proposal {
   pub network: Network,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum ProposalCategory {
   Core,
   Networking,
@@ -32,8 +92,103 @@ pub enum ProposalCategory {
   Cryptography,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// A MEF funding round: proposals are first voted into consideration one by
+/// one, then the considered ones are ranked.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FundingRound {
+  pub id: usize,
+  pub network: Network,
+  /// When the candidate proposals are voted into consideration.
+  pub consideration: VotingWindow,
+  /// When the candidate proposals are ranked.
+  pub ranking: VotingWindow,
+  /// The staking ledger votes are weighted with.
+  pub ledger_hash: Option<String>,
+  /// The ids of the candidate proposals.
+  pub proposals: Vec<usize>,
+  /// The rules the ranked vote of the round is run with. Rules left out take
+  /// their default value.
+  #[serde(default)]
+  pub vote_rules: VoteRules,
+}
+
+/// A voting period, in milliseconds since the epoch.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct VotingWindow {
+  pub start_time: i64,
+  pub end_time: i64,
+}
+
+/// How the stake of the staking ledger is attributed to votes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum ProposalVersion {
+  /// A voter is weighted with its balance and those of all its delegators,
+  /// unless it delegates away, in which case its vote has no weight.
   V1,
+  /// A voter is weighted with its balance and those of its delegators which
+  /// did not vote themselves.
   V2,
+  /// Every ledger account's balance is counted exactly once: for its own
+  /// vote if it voted, otherwise for its delegate's vote if the delegate
+  /// voted, otherwise not at all.
+  V3,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validate_manifest() {
+    let manifest: ProposalsManifest = serde_json::from_slice(include_bytes!("../proposals/proposals.json")).unwrap();
+    assert_eq!(manifest.validate(), Vec::<String>::new());
+
+    let mut invalid = manifest.clone();
+    invalid.proposals[1].id = 0;
+    invalid.proposals[2].key = "mip1".to_string();
+    invalid.proposals[3].start_time = invalid.proposals[3].end_time;
+    invalid.proposals[4].key = "a".repeat(30);
+    invalid.proposals[5].key = "MEF1 YES 2".to_string();
+    assert_eq!(invalid.validate(), vec![
+      "proposal 0: duplicate id",
+      "proposal 2: duplicate key mip1 on mainnet",
+      "proposal 3: start_time is not before end_time",
+      &format!("proposal 4: key {} does not fit in a memo", "a".repeat(30)),
+      "proposal 5: key MEF1 YES 2 is not read as a vote on it",
+    ]);
+  }
+
+  #[test]
+  fn test_funding_rounds() {
+    let round = |id: usize, rules: &str| {
+      format!(
+        r#"{{"id": {id}, "network": "mainnet", "consideration": {{"start_time": 0, "end_time": 10}},
+          "ranking": {{"start_time": 20, "end_time": 30}}, "ledger_hash": "jx", "proposals": [1, 2]{rules}}}"#
+      )
+    };
+    let json = format!(
+      r#"{{"proposals": [], "rounds": [{}, {}, {}]}}"#,
+      round(1, ""),
+      round(2, r#", "vote_rules": {"tiebreak_mode": {"random": 42}, "elimination_algorithm": "batch"}"#),
+      round(2, "")
+    );
+    let mut manifest: ProposalsManifest = serde_json::from_str(&json).unwrap();
+    assert_eq!(manifest.rounds[0].vote_rules, VoteRules::default());
+    assert_eq!(manifest.rounds[0].ranking, VotingWindow { start_time: 20, end_time: 30 });
+    let rules = &manifest.rounds[1].vote_rules;
+    assert_eq!(rules.tiebreak_mode, crate::TieBreakMode::Random(42));
+    assert_eq!(rules.elimination_algorithm, crate::EliminationAlgorithm::Batch);
+    assert_eq!(rules.max_rankings_allowed, Some(10));
+    assert_eq!(manifest.validate(), vec!["round 2: duplicate id on mainnet"]);
+
+    manifest.rounds.truncate(1);
+    manifest.rounds[0].ranking.end_time = 20;
+    manifest.rounds[0].proposals = vec![1, 1];
+    manifest.rounds[0].ledger_hash = Some(String::new());
+    assert_eq!(manifest.validate(), vec![
+      "round 1: ranking start_time is not before end_time",
+      "round 1: duplicate candidate proposals",
+      "round 1: empty ledger_hash",
+    ]);
+  }
 }
@@ -4,14 +4,15 @@ use std::{
   ops::{Add, AddAssign},
 };
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use tracing::log::{debug, error, info};
 
 use crate::{
   Ballot, BallotChoice, Builder, Candidate, DuplicateCandidateMode, ElectionResult, ElectionStats,
-  EliminationAlgorithm, EliminationStats, MaxSkippedRank, OverVoteRule, RoundStats, TieBreakMode, VoteRules,
-  VotingErrors, VotingResult, Wrapper, archive::FetchTransactionResult, vote::BlockStatus,
+  EliminationAlgorithm, EliminationStats, MaxSkippedRank, MemoError, OverVoteRule, RejectedVote, RejectionReason,
+  RoundStats, TieBreakMode, VoteMemo, VoteRules, VotingErrors, VotingResult, Wrapper, archive::FetchTransactionResult,
+  decode_memo, vote::BlockStatus,
 };
 
 // **** Private structures ****
@@ -86,41 +87,33 @@ impl RankedVote {
     self.height > other.height || (self.height == other.height && self.nonce > other.nonce)
   }
 
-  pub(crate) fn decode_memo(&self) -> Result<String> {
-    let decoded =
-      bs58::decode(&self.memo).into_vec().with_context(|| format!("failed to decode memo {} - bs58", &self.memo))?;
-
-    let value = &decoded[3 .. decoded[2] as usize + 3];
+  pub fn reject(self, reason: RejectionReason) -> RejectedVote {
+    RejectedVote {
+      account: self.account,
+      hash: self.hash,
+      memo: self.memo,
+      height: self.height,
+      status: self.status,
+      timestamp: self.timestamp,
+      nonce: self.nonce,
+      reason,
+    }
+  }
 
-    let result =
-      String::from_utf8(value.to_vec()).with_context(|| format!("failed to decode memo {} - from_utf8", &self.memo))?;
-    Ok(result)
+  pub(crate) fn decode_memo(&self) -> Result<String, MemoError> {
+    decode_memo(&self.memo)
   }
 
   pub fn parse_decoded_ranked_votes_memo(&mut self, key: &str) -> Option<(String, Vec<String>)> {
-    if let Ok(decoded) = self.decode_memo() {
-      let decoded = decoded.to_lowercase();
-
-      // Split the decoded memo into parts by whitespace
-      let mut parts = decoded.split_whitespace();
-
-      // Check if the first part is the "MEF" prefix
-      if let Some(prefix) = parts.next() {
-        if prefix == "mef" {
-          // Extract the round_id
-          if let Some(round_id) = parts.next() {
-            if round_id == key {
-              // Collect remaining parts as proposal IDs
-              let proposal_ids: Vec<String> = parts.map(|id| id.to_string()).collect();
-              tracing::info!("decoded memo: {}", decoded);
-              tracing::info!("proposals: {:?}", proposal_ids);
-              return Some((round_id.to_string(), proposal_ids));
-            }
-          }
-        }
+    let decoded = self.decode_memo().ok()?;
+    match VoteMemo::parse(&decoded)? {
+      VoteMemo::MefRanked { round_id, choices } if round_id.to_string() == key => {
+        tracing::info!("decoded memo: {}", decoded);
+        tracing::info!("proposals: {:?}", choices);
+        Some((key.to_string(), choices))
       }
+      _ => None,
     }
-    None
   }
 }
 
@@ -163,38 +156,62 @@ impl From<FetchTransactionResult> for RankedVote {
   }
 }
 impl Wrapper<Vec<RankedVote>> {
-  pub fn process_ranked_vote(self, id: usize, tip: i64) -> Wrapper<BTreeMap<String, RankedVote>> {
+  pub fn process_ranked_vote(self, id: usize, tip: i64, finality_depth: i64) -> Wrapper<BTreeMap<String, RankedVote>> {
+    self.process_ranked_vote_with_rejections(id, tip, finality_depth).0
+  }
+
+  /// Like [`Self::process_ranked_vote`], also returning the transactions
+  /// which were not counted.
+  pub fn process_ranked_vote_with_rejections(
+    self,
+    id: usize,
+    tip: i64,
+    finality_depth: i64,
+  ) -> (Wrapper<BTreeMap<String, RankedVote>>, Vec<RejectedVote>) {
     let mut map = BTreeMap::new();
+    let mut rejected = Vec::new();
     let id_str = id.to_string();
 
     for mut vote in self.0 {
-      // Use the updated `match_decoded_ranked_vote_memo` function
-      if let Some((_round_id, proposal_ids)) = vote.parse_decoded_ranked_votes_memo(&id_str) {
-        // Update the memo with proposal IDs
-        vote.update_memo(format!("Votes: {:?}", proposal_ids));
-        vote.proposals = proposal_ids;
-        // Update vote status if conditions are met
-        if tip - vote.height >= 10 {
-          vote.update_status(BlockStatus::Canonical);
-        }
-
-        // Insert into the map based on account
-        match map.entry(vote.account.clone()) {
-          Entry::Vacant(e) => {
-            e.insert(vote);
-          }
-          Entry::Occupied(mut e) => {
-            let current_vote = e.get_mut();
-            if vote.is_newer_than(current_vote) {
-              // Allow updating the vote if it is newer
-              *current_vote = vote;
-            }
+      if vote.status == BlockStatus::Orphaned {
+        rejected.push(vote.reject(RejectionReason::Orphaned));
+        continue;
+      }
+      let Some((_round_id, proposal_ids)) = vote.parse_decoded_ranked_votes_memo(&id_str) else {
+        let reason = match vote.decode_memo() {
+          Ok(decoded) => {
+            vote.update_memo(decoded);
+            RejectionReason::WrongKey
           }
+          Err(_) => RejectionReason::UndecodableMemo,
+        };
+        rejected.push(vote.reject(reason));
+        continue;
+      };
+      // Update the memo with proposal IDs
+      vote.update_memo(format!("Votes: {:?}", proposal_ids));
+      vote.proposals = proposal_ids;
+      // Update vote status if conditions are met
+      if vote.status == BlockStatus::Pending && tip - vote.height >= finality_depth {
+        vote.update_status(BlockStatus::Canonical);
+      }
+
+      // Insert into the map based on account
+      match map.entry(vote.account.clone()) {
+        Entry::Vacant(e) => {
+          e.insert(vote);
+        }
+        Entry::Occupied(mut e) => {
+          let current_vote = e.get_mut();
+          // Allow updating the vote if it is newer
+          let superseded = if vote.is_newer_than(current_vote) { std::mem::replace(current_vote, vote) } else { vote };
+          rejected.push(superseded.reject(RejectionReason::SupersededBy { hash: String::new() }));
         }
       }
     }
 
-    Wrapper(map)
+    RejectedVote::resolve_superseded(&mut rejected, |account| map.get(account).map(|vote| vote.hash.clone()));
+    (Wrapper(map), rejected)
   }
 }
 
@@ -303,18 +320,37 @@ pub fn run_election(builder: &Builder) -> Result<ElectionResult, VotingErrors> {
 /// Runs an election (simple interface) using the instant-runoff voting
 /// algorithm.
 pub fn run_simple_election(votes: &[Vec<&str>], rules: &VoteRules) -> Result<ElectionResult, VotingErrors> {
+  let ballots: Vec<(Vec<&str>, u64)> = votes.iter().map(|choices| (choices.clone(), 1)).collect();
+  run_weighted_election(&ballots, None, rules)
+}
+
+/// Runs an election using the instant-runoff voting algorithm, each ballot
+/// counting as many times as its weight.
+///
+/// Without `candidates`, every choice on a ballot is a candidate. Otherwise
+/// other choices are undeclared write-ins.
+pub fn run_weighted_election(
+  ballots: &[(Vec<&str>, u64)],
+  candidates: Option<&[String]>,
+  rules: &VoteRules,
+) -> Result<ElectionResult, VotingErrors> {
   let mut builder = Builder::new(rules)?;
-  let mut cand_set: BTreeSet<String> = BTreeSet::new();
-  for ballot in votes.iter() {
-    for choice in ballot.iter() {
-      cand_set.insert(choice.to_string());
+  let cand_vec: Vec<String> = match candidates {
+    Some(candidates) => candidates.to_vec(),
+    None => {
+      let mut cand_set: BTreeSet<String> = BTreeSet::new();
+      for (choices, _) in ballots.iter() {
+        for choice in choices.iter() {
+          cand_set.insert(choice.to_string());
+        }
+      }
+      cand_set.into_iter().collect()
     }
-  }
-  let cand_vec: Vec<String> = cand_set.iter().cloned().collect();
+  };
   builder = builder.candidates(&cand_vec)?;
-  for choices in votes.iter() {
+  for (choices, weight) in ballots.iter() {
     let cands: Vec<Vec<String>> = choices.iter().map(|c| vec![c.to_string()]).collect();
-    builder.add_vote(&cands, 1)?;
+    builder.add_vote(&cands, *weight)?;
   }
   run_election(&builder)
 }
@@ -1184,7 +1220,7 @@ mod tests {
   #[test]
   fn test_process_ranked_votes() {
     let votes = get_test_votes();
-    let binding = Wrapper(votes).process_ranked_vote(1, 129);
+    let binding = Wrapper(votes).process_ranked_vote(1, 129, 10);
     let processed: Vec<RankedVote> = binding.0.values().cloned().collect();
 
     assert_eq!(processed.len(), 9);
@@ -1287,6 +1323,24 @@ mod tests {
     assert_eq!(result.winners.unwrap(), vec!["2", "4", "1", "3", "39", "5"]);
   }
 
+  #[test]
+  fn test_run_weighted_election() {
+    let ballots = vec![(vec!["1", "2"], 1), (vec!["1", "2"], 1), (vec!["2", "1"], 5_000_000_000)];
+    let rules = VoteRules::default();
+
+    let result = run_weighted_election(&ballots, None, &rules).unwrap();
+    assert_eq!(result.winners.unwrap(), vec!["2", "1"]);
+    assert_eq!(result.stats[0].round_stats[0].tally, vec![("1".to_string(), 2), ("2".to_string(), 5_000_000_000)]);
+
+    let headcount: Vec<Vec<&str>> = ballots.iter().map(|(choices, _)| choices.clone()).collect();
+    assert_eq!(run_simple_election(&headcount, &rules).unwrap().winners.unwrap(), vec!["1", "2"]);
+
+    // A choice which is not a candidate is skipped.
+    let candidates = ["1".to_string()];
+    let result = run_weighted_election(&ballots, Some(&candidates), &rules).unwrap();
+    assert_eq!(result.winners.unwrap(), vec!["1"]);
+  }
+
   fn get_test_votes() -> Vec<RankedVote> {
     vec![
       RankedVote::new(
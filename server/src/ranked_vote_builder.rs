@@ -32,7 +32,7 @@ impl Builder {
   ///
   /// candidates: the list of choices made by the voter, in order. Choices do
   /// not need to be unique, or distinct or non-empty.
-  pub fn add_vote(&mut self, candidates: &[Vec<String>], count: u32) -> Result<(), VotingErrors> {
+  pub fn add_vote(&mut self, candidates: &[Vec<String>], count: u64) -> Result<(), VotingErrors> {
     let mut choices: Vec<BallotChoice> = Vec::new();
     for c in candidates {
       let cand = match c.as_slice() {
@@ -53,7 +53,7 @@ impl Builder {
       };
       choices.push(cand);
     }
-    self.add_vote_2(&Ballot { count: count as u64, candidates: choices })
+    self.add_vote_2(&Ballot { count, candidates: choices })
   }
 
   pub fn add_vote_2(&mut self, vote: &Ballot) -> Result<(), VotingErrors> {
@@ -125,7 +125,8 @@ impl Display for VotingErrors {
 
 // The configuration options
 /// The different modes to break a tie in case of multiple counts.
-#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TieBreakMode {
   /// Uses the order in which the candidates have been declared.
   /// The first candidate in the list will have priority over all other
@@ -146,7 +147,8 @@ pub enum TieBreakMode {
 /// - the ballot will be exhausted (discarded) under `ExhaustImmediately`
 /// - under AlwaysSkipToNextRank, the initial `["A", "B"]` choice will be
 ///   discarded and `"C"` will be considered instead.
-#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum OverVoteRule {
   /// The ballot is exhausted (discarded).
   ExhaustImmediately,
@@ -161,7 +163,8 @@ pub enum OverVoteRule {
 /// - with Exhaust, this ballot would be entirely discarded
 /// - with SkipDuplicate, this ballot would be equivalent to reducing `B` to
 ///   only a single instance: `[B, C]`.
-#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DuplicateCandidateMode {
   Exhaust,
   SkipDuplicate,
@@ -174,7 +177,8 @@ pub enum DuplicateCandidateMode {
 ///   with a comparatively very low number of votes.
 ///
 /// - Batch eliminates candidates more rapidly.
-#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum EliminationAlgorithm {
   Batch,
   Single,
@@ -194,7 +198,8 @@ pub enum EliminationAlgorithm {
 /// - disallow the ballot: `[OVERVOTE, BLANK, BLANK, BLANK, A]`
 ///
 /// Default: `Unlimited`.
-#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MaxSkippedRank {
   Unlimited,
   ExhaustOnFirstOccurence,
@@ -205,7 +210,8 @@ pub enum MaxSkippedRank {
 ///
 /// The easiest way to use them is to use a default instance of the rules and
 /// modify them.
-#[derive(Eq, PartialEq, Debug, Clone)]
+#[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VoteRules {
   /// Tie break mode
   pub tiebreak_mode: TieBreakMode,
@@ -0,0 +1,59 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{ArchiveInterface, BlockStatus, GetMinaProposalResultResponse, Ocv, Proposal};
+
+/// A final proposal result, along with the inputs it was computed from.
+///
+/// Once a proposal's voting window has closed and all of its votes are final,
+/// its result is written to `results_storage_path` and served from there from
+/// then on, instead of being recomputed from the archive and the ledger.
+#[derive(Serialize, Deserialize)]
+pub struct StoredProposalResult {
+  pub ledger_hash: String,
+  pub chain_tip: i64,
+  pub result: GetMinaProposalResultResponse,
+}
+
+impl StoredProposalResult {
+  pub fn load<A: ArchiveInterface>(ocv: &Ocv<A>, proposal: &Proposal) -> Result<Option<StoredProposalResult>> {
+    let path = Self::path(ocv, proposal);
+    if !path.exists() {
+      return Ok(None);
+    }
+    let contents = fs::read(&path)?;
+    let stored = serde_json::from_slice(&contents).with_context(|| format!("invalid stored result {path:?}"))?;
+    Ok(Some(stored))
+  }
+
+  /// Whether this result was computed for `proposal` as it now stands in the
+  /// manifest, rather than for an entry since corrected.
+  pub fn is_for(&self, proposal: &Proposal) -> bool {
+    proposal.ledger_hash.as_ref() == Some(&self.ledger_hash) && self.result.proposal == *proposal
+  }
+
+  pub fn save<A: ArchiveInterface>(&self, ocv: &Ocv<A>) -> Result<()> {
+    let path = Self::path(ocv, &self.result.proposal);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+    fs::rename(tmp, path)?;
+    Ok(())
+  }
+
+  /// Whether a result can no longer change: the last final block of the
+  /// archive, with timestamp `final_block_timestamp`, is past the end of the
+  /// voting window and every vote counted is in a final block.
+  ///
+  /// The window is checked against the archive rather than the wall clock,
+  /// as an archive lagging behind the chain may not have every vote yet.
+  pub fn is_final(result: &GetMinaProposalResultResponse, final_block_timestamp: Option<i64>) -> bool {
+    final_block_timestamp.is_some_and(|timestamp| timestamp > result.proposal.end_time)
+      && result.votes.iter().all(|vote| vote.status == BlockStatus::Canonical)
+  }
+
+  fn path<A: ArchiveInterface>(ocv: &Ocv<A>, proposal: &Proposal) -> PathBuf {
+    ocv.results_storage_path.join(format!("{}-proposal-{}.json", proposal.network, proposal.id))
+  }
+}
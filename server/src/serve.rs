@@ -1,10 +1,14 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use anyhow::Result;
 use axum::{
-  Json, Router, debug_handler,
-  extract::{Path, Query, State},
-  response::IntoResponse,
+  Json, Router,
+  extract::{FromRef, Path, Query, State},
+  http::header,
+  response::{
+    IntoResponse, Response,
+    sse::{KeepAlive, Sse},
+  },
   routing::get,
   serve as axum_serve,
 };
@@ -12,7 +16,7 @@ use clap::Parser;
 use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
 
-use crate::{Ocv, OcvConfig, Wrapper, shutdown_signal};
+use crate::{ArchiveInterface, LiveTallies, LiveTopic, Ocv, OcvConfig, Wrapper, live_events, shutdown_signal};
 
 #[derive(Clone, Parser)]
 pub struct ServeArgs {
@@ -22,6 +26,9 @@ pub struct ServeArgs {
   /// API Port.
   #[clap(long, env, default_value = "8080")]
   pub port: u16,
+  /// How often live tallies poll the archive for new blocks, in seconds.
+  #[clap(long, env, default_value = "10")]
+  pub live_poll_interval: u64,
   /// OCV Args.
   #[command(flatten)]
   pub config: OcvConfig,
@@ -31,54 +38,224 @@ impl ServeArgs {
   pub async fn serve(&self) -> Result<()> {
     tracing_subscriber::fmt::init();
 
+    let ocv = self.config.to_ocv().await?;
+    self.serve_with(ocv).await
+  }
+
+  /// Serves the API against an already constructed [`Ocv`], whichever
+  /// archive it is backed by.
+  pub async fn serve_with<A: ArchiveInterface>(&self, ocv: Ocv<A>) -> Result<()> {
     let listener = TcpListener::bind(format!("{}:{}", self.host, self.port)).await?;
     tracing::info!("Starting server at http://{}.", listener.local_addr()?);
 
-    let ocv = self.config.to_ocv().await?;
-    let router = Router::new()
-      .route("/api/info", get(get_info))
-      .route("/api/proposals", get(get_proposals))
-      .route("/api/proposal/:id", get(get_proposal))
-      .route("/api/proposal/:id/results", get(get_proposal_result))
-      .route(
-        "/api/mef_proposal_consideration/:round_id/:proposal_id/:start_time/:end_time",
-        get(get_proposal_consideration),
-      )
-      .route("/api/mef_ranked_vote/:round_id/:start_time/:end_time", get(run_ranked_vote))
-      .layer(CorsLayer::permissive())
-      .with_state(Arc::new(ocv));
+    let router = router(ocv, Duration::from_secs(self.live_poll_interval));
     axum_serve(listener, router).with_graceful_shutdown(shutdown_signal()).await?;
     Ok(())
   }
 }
 
-#[debug_handler]
-async fn get_info(ctx: State<Arc<Ocv>>) -> impl IntoResponse {
+/// The state shared by the handlers: the instance, and the live tallies
+/// polled from its archive.
+struct ApiState<A: ArchiveInterface> {
+  ocv: Arc<Ocv<A>>,
+  live: Arc<LiveTallies<A>>,
+}
+
+impl<A: ArchiveInterface> Clone for ApiState<A> {
+  fn clone(&self) -> Self {
+    ApiState { ocv: self.ocv.clone(), live: self.live.clone() }
+  }
+}
+
+impl<A: ArchiveInterface> FromRef<ApiState<A>> for Arc<Ocv<A>> {
+  fn from_ref(state: &ApiState<A>) -> Self {
+    state.ocv.clone()
+  }
+}
+
+impl<A: ArchiveInterface> FromRef<ApiState<A>> for Arc<LiveTallies<A>> {
+  fn from_ref(state: &ApiState<A>) -> Self {
+    state.live.clone()
+  }
+}
+
+/// The API routes. Must be called within a Tokio runtime, which runs the
+/// poller of the live tallies.
+pub fn router<A: ArchiveInterface>(ocv: Ocv<A>, live_poll_interval: Duration) -> Router {
+  let ocv = Arc::new(ocv);
+  let live = LiveTallies::spawn(ocv.clone(), live_poll_interval);
+  Router::new()
+    .route("/api/info", get(get_info))
+    .route("/api/proposals", get(get_proposals))
+    .route("/api/proposal/:id", get(get_proposal))
+    .route("/api/proposal/:id/results", get(get_proposal_result))
+    .route("/api/proposal/:id/attestation", get(get_proposal_attestation))
+    .route("/api/proposal/:id/rejections", get(get_proposal_rejections))
+    .route("/api/proposal/:id/voter/:public_key", get(get_proposal_voter))
+    .route("/api/proposal/:id/live", get(get_proposal_live))
+    .route("/api/rounds", get(get_rounds))
+    .route("/api/round/:round_id", get(get_round))
+    .route("/api/round/:round_id/consideration/:proposal_id", get(get_round_consideration))
+    .route("/api/round/:round_id/consideration/:proposal_id/rejections", get(get_round_consideration_rejections))
+    .route("/api/round/:round_id/consideration/:proposal_id/voter/:public_key", get(get_round_consideration_voter))
+    .route("/api/round/:round_id/ranked_vote", get(get_round_ranked_vote))
+    .route("/api/round/:round_id/ranked_vote/rejections", get(get_round_ranked_vote_rejections))
+    .route("/api/round/:round_id/ranked_vote/voter/:public_key", get(get_round_ranked_voter))
+    .route("/api/round/:round_id/live", get(get_round_live))
+    // What-if mode: MEF rounds tallied over a window and ledger chosen by the
+    // caller, rather than those of the round in the manifest.
+    .route(
+      "/api/mef_proposal_consideration/:round_id/:proposal_id/:start_time/:end_time",
+      get(get_proposal_consideration),
+    )
+    .route(
+      "/api/mef_proposal_consideration/:round_id/:proposal_id/:start_time/:end_time/rejections",
+      get(get_consideration_rejections),
+    )
+    .route(
+      "/api/mef_proposal_consideration/:round_id/:proposal_id/:start_time/:end_time/voter/:public_key",
+      get(get_consideration_voter),
+    )
+    .route("/api/mef_ranked_vote/:round_id/:start_time/:end_time", get(run_ranked_vote))
+    .route("/api/mef_ranked_vote/:round_id/:start_time/:end_time/rejections", get(get_ranked_vote_rejections))
+    .route("/api/mef_ranked_vote/:round_id/:start_time/:end_time/voter/:public_key", get(get_ranked_voter))
+    .layer(CorsLayer::permissive())
+    .with_state(ApiState { ocv, live })
+}
+
+async fn get_info<A: ArchiveInterface>(ctx: State<Arc<Ocv<A>>>) -> impl IntoResponse {
   tracing::info!("get_info");
   Wrapper(ctx.info().await)
 }
 
-#[debug_handler]
-async fn get_proposals(ctx: State<Arc<Ocv>>) -> impl IntoResponse {
+async fn get_proposals<A: ArchiveInterface>(ctx: State<Arc<Ocv<A>>>) -> impl IntoResponse {
   tracing::info!("get_proposals");
   Json(ctx.proposals.to_owned())
 }
 
-#[debug_handler]
-async fn get_proposal(ctx: State<Arc<Ocv>>, Path(id): Path<usize>) -> impl IntoResponse {
+async fn get_proposal<A: ArchiveInterface>(ctx: State<Arc<Ocv<A>>>, Path(id): Path<usize>) -> impl IntoResponse {
   tracing::info!("get_proposal {}", id);
   Wrapper(ctx.proposal(id).await)
 }
 
-#[debug_handler]
-async fn get_proposal_result(ctx: State<Arc<Ocv>>, Path(id): Path<usize>) -> impl IntoResponse {
+async fn get_proposal_result<A: ArchiveInterface>(ctx: State<Arc<Ocv<A>>>, Path(id): Path<usize>) -> impl IntoResponse {
   tracing::info!("get_proposal_result {}", id);
   Wrapper(ctx.proposal_result(id).await)
 }
 
-#[debug_handler]
-async fn get_proposal_consideration(
-  ctx: State<Arc<Ocv>>,
+async fn get_proposal_attestation<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path(id): Path<usize>,
+) -> impl IntoResponse {
+  tracing::info!("get_proposal_attestation {}", id);
+  let filename = format!("attachment; filename=\"proposal-{id}-attestation.json\"");
+  ([(header::CONTENT_DISPOSITION, filename)], Wrapper(ctx.proposal_attestation(id).await))
+}
+
+async fn get_proposal_rejections<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path(id): Path<usize>,
+  Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+  let account = params.get("account").map(String::as_str);
+  tracing::info!("get_proposal_rejections {} {:?}", id, account);
+  Wrapper(ctx.proposal_rejections(id, account).await)
+}
+
+async fn get_proposal_voter<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path((id, public_key)): Path<(usize, String)>,
+) -> impl IntoResponse {
+  tracing::info!("get_proposal_voter {} {}", id, public_key);
+  Wrapper(ctx.proposal_voter(id, &public_key).await)
+}
+
+async fn get_proposal_live<A: ArchiveInterface>(live: State<Arc<LiveTallies<A>>>, Path(id): Path<usize>) -> Response {
+  tracing::info!("get_proposal_live {}", id);
+  live_response(&live, LiveTopic::Proposal(id))
+}
+
+async fn get_rounds<A: ArchiveInterface>(ctx: State<Arc<Ocv<A>>>) -> impl IntoResponse {
+  tracing::info!("get_rounds");
+  Json(ctx.rounds.to_owned())
+}
+
+async fn get_round<A: ArchiveInterface>(ctx: State<Arc<Ocv<A>>>, Path(round_id): Path<usize>) -> impl IntoResponse {
+  tracing::info!("get_round {}", round_id);
+  Wrapper(ctx.round(round_id))
+}
+
+async fn get_round_consideration<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path((round_id, proposal_id)): Path<(usize, usize)>,
+) -> impl IntoResponse {
+  tracing::info!("get_round_consideration {} {}", round_id, proposal_id);
+  Wrapper(ctx.round_consideration(round_id, proposal_id).await)
+}
+
+async fn get_round_consideration_rejections<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path((round_id, proposal_id)): Path<(usize, usize)>,
+  Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+  let account = params.get("account").map(String::as_str);
+  tracing::info!("get_round_consideration_rejections {} {} {:?}", round_id, proposal_id, account);
+  Wrapper(ctx.round_consideration_rejections(round_id, proposal_id, account).await)
+}
+
+async fn get_round_consideration_voter<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path((round_id, proposal_id, public_key)): Path<(usize, usize, String)>,
+) -> impl IntoResponse {
+  tracing::info!("get_round_consideration_voter {} {} {}", round_id, proposal_id, public_key);
+  Wrapper(ctx.round_consideration_voter(round_id, proposal_id, &public_key).await)
+}
+
+async fn get_round_ranked_vote<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path(round_id): Path<usize>,
+) -> impl IntoResponse {
+  tracing::info!("get_round_ranked_vote {}", round_id);
+  Wrapper(ctx.round_ranked_vote(round_id).await)
+}
+
+async fn get_round_ranked_vote_rejections<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path(round_id): Path<usize>,
+  Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+  let account = params.get("account").map(String::as_str);
+  tracing::info!("get_round_ranked_vote_rejections {} {:?}", round_id, account);
+  Wrapper(ctx.round_ranked_vote_rejections(round_id, account).await)
+}
+
+async fn get_round_ranked_voter<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path((round_id, public_key)): Path<(usize, String)>,
+) -> impl IntoResponse {
+  tracing::info!("get_round_ranked_voter {} {}", round_id, public_key);
+  Wrapper(ctx.round_ranked_voter(round_id, &public_key).await)
+}
+
+async fn get_round_live<A: ArchiveInterface>(
+  live: State<Arc<LiveTallies<A>>>,
+  Path(round_id): Path<usize>,
+) -> Response {
+  tracing::info!("get_round_live {}", round_id);
+  live_response(&live, LiveTopic::Round(round_id))
+}
+
+/// Server-sent events of the votes and tally of `topic`, as new blocks reach
+/// the archive.
+fn live_response<A: ArchiveInterface>(live: &LiveTallies<A>, topic: LiveTopic) -> Response {
+  match live.subscribe(topic) {
+    Ok(receiver) => Sse::new(live_events(receiver)).keep_alive(KeepAlive::default()).into_response(),
+    Err(error) => Wrapper(Err::<(), _>(error)).into_response(),
+  }
+}
+
+async fn get_proposal_consideration<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
   Path((round_id, proposal_id, start_time, end_time)): Path<(usize, usize, i64, i64)>,
   Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
@@ -87,9 +264,18 @@ async fn get_proposal_consideration(
   Wrapper(ctx.proposal_consideration(round_id, proposal_id, start_time, end_time, ledger_hash).await)
 }
 
-#[debug_handler]
-async fn run_ranked_vote(
-  ctx: State<Arc<Ocv>>,
+async fn get_consideration_voter<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path((round_id, proposal_id, start_time, end_time, public_key)): Path<(usize, usize, i64, i64, String)>,
+  Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+  let ledger_hash = params.get("ledger_hash").cloned();
+  tracing::info!("get_consideration_voter {} {} {} {} {}", round_id, proposal_id, start_time, end_time, public_key);
+  Wrapper(ctx.consideration_voter(round_id, proposal_id, start_time, end_time, ledger_hash, &public_key).await)
+}
+
+async fn run_ranked_vote<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
   Path((round_id, start_time, end_time)): Path<(usize, i64, i64)>,
   Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
@@ -97,3 +283,34 @@ async fn run_ranked_vote(
   tracing::info!("run_ranked_vote {} {} {}", round_id, start_time, end_time);
   Wrapper(ctx.run_ranked_vote(round_id, start_time, end_time, ledger_hash).await)
 }
+
+async fn get_consideration_rejections<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path((round_id, proposal_id, start_time, end_time)): Path<(usize, usize, i64, i64)>,
+  Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+  let ledger_hash = params.get("ledger_hash").cloned();
+  let account = params.get("account").map(String::as_str);
+  tracing::info!("get_consideration_rejections {} {} {} {} {:?}", round_id, proposal_id, start_time, end_time, account);
+  Wrapper(ctx.consideration_rejections(round_id, proposal_id, start_time, end_time, ledger_hash, account).await)
+}
+
+async fn get_ranked_vote_rejections<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path((round_id, start_time, end_time)): Path<(usize, i64, i64)>,
+  Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+  let account = params.get("account").map(String::as_str);
+  tracing::info!("get_ranked_vote_rejections {} {} {} {:?}", round_id, start_time, end_time, account);
+  Wrapper(ctx.ranked_vote_rejections(round_id, start_time, end_time, account).await)
+}
+
+async fn get_ranked_voter<A: ArchiveInterface>(
+  ctx: State<Arc<Ocv<A>>>,
+  Path((round_id, start_time, end_time, public_key)): Path<(usize, i64, i64, String)>,
+  Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+  let ledger_hash = params.get("ledger_hash").cloned();
+  tracing::info!("get_ranked_voter {} {} {} {}", round_id, start_time, end_time, public_key);
+  Wrapper(ctx.ranked_voter(round_id, start_time, end_time, ledger_hash, &public_key).await)
+}
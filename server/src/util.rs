@@ -1,9 +1,7 @@
 mod caches;
-mod s3;
 mod shutdown_signal;
 mod wrapper;
 
 pub use caches::Caches;
-pub use s3::s3_client;
 pub use shutdown_signal::shutdown_signal;
 pub use wrapper::Wrapper;
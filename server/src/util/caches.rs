@@ -1,24 +1,46 @@
 use std::sync::Arc;
 
 use moka::future::Cache as MokaCache;
+use tokio::sync::Mutex;
 
-use crate::{RankedVote, Vote, VoteWithWeight, ledger::LedgerAccount};
+use crate::{RankedVote, Vote, VoteIngestion, VoteWithWeight, ledger::Ledger};
 
 #[derive(Clone)]
 pub struct Caches {
   pub votes: MokaCache<String, Arc<Vec<Vote>>>,
-  pub votes_weighted: MokaCache<String, Arc<Vec<VoteWithWeight>>>,
-  pub ledger: MokaCache<String, Arc<Vec<LedgerAccount>>>,
+  /// Weighted votes, by the key of their votes and the hash of the ledger
+  /// they were weighted with.
+  pub votes_weighted: MokaCache<(String, String), Arc<Vec<VoteWithWeight>>>,
+  pub ledger: MokaCache<String, Arc<Ledger>>,
   pub ranked_votes: MokaCache<String, Arc<Vec<RankedVote>>>,
+  pub ingestion: MokaCache<String, Arc<Mutex<VoteIngestion>>>,
 }
 
 impl Caches {
   pub fn build() -> Self {
     Self {
-      votes: MokaCache::builder().time_to_live(std::time::Duration::from_secs(60 * 5)).build(),
-      votes_weighted: MokaCache::builder().time_to_live(std::time::Duration::from_secs(60 * 5)).build(),
-      ledger: MokaCache::builder().time_to_live(std::time::Duration::from_secs(60 * 60 * 12)).build(),
-      ranked_votes: MokaCache::builder().time_to_live(std::time::Duration::from_secs(60 * 5)).build(),
+      votes: MokaCache::builder().max_capacity(1_000).time_to_live(std::time::Duration::from_secs(60 * 5)).build(),
+      votes_weighted: MokaCache::builder()
+        .max_capacity(1_000)
+        .time_to_live(std::time::Duration::from_secs(60 * 5))
+        .support_invalidation_closures()
+        .build(),
+      ledger: MokaCache::builder().max_capacity(16).time_to_live(std::time::Duration::from_secs(60 * 60 * 12)).build(),
+      ranked_votes: MokaCache::builder()
+        .max_capacity(1_000)
+        .time_to_live(std::time::Duration::from_secs(60 * 5))
+        .build(),
+      ingestion: MokaCache::builder().max_capacity(1_000).time_to_live(std::time::Duration::from_secs(60 * 60)).build(),
     }
   }
+
+  /// Evicts the votes of `key`, weighted with any ledger.
+  pub async fn invalidate_key(&self, key: &str) {
+    self.votes.invalidate(key).await;
+    let key = key.to_string();
+    self
+      .votes_weighted
+      .invalidate_entries_if(move |(votes_key, _), _| *votes_key == key)
+      .expect("Invalidation closures are enabled for weighted votes");
+  }
 }
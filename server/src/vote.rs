@@ -1,12 +1,15 @@
-use std::collections::{HashMap, hash_map::Entry};
+use std::collections::{HashMap, HashSet, hash_map::Entry};
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use diesel::SqlType;
 use diesel_derive_enum::DbEnum;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-use crate::{Proposal, Wrapper, archive::FetchTransactionResult, ledger::Ledger};
+use crate::{
+  ConsiderationChoice, MemoError, Proposal, ProposalVersion, VoteMemo, Wrapper, archive::FetchTransactionResult,
+  decode_memo, ledger::Ledger,
+};
 
 #[derive(SqlType)]
 #[diesel(postgres_type(name = "chain_status_type"))]
@@ -43,6 +46,59 @@ pub struct Vote {
   pub nonce: i64,
 }
 
+/// Why a transaction was not counted as a vote, or was counted without any
+/// weight.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum RejectionReason {
+  /// The memo is not a valid user memo.
+  UndecodableMemo,
+  /// The memo is not a vote on this proposal.
+  WrongKey,
+  /// The account voted again later with the transaction `hash`.
+  SupersededBy { hash: String },
+  /// The account is not in the staking ledger of the proposal.
+  NotInLedger,
+  /// The account delegates its stake, so its vote carries no weight.
+  DelegatedAway { delegate: String },
+  /// The block of the transaction has been orphaned.
+  Orphaned,
+}
+
+/// A transaction which was not counted as a vote, and why.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct RejectedVote {
+  pub account: String,
+  pub hash: String,
+  /// The decoded memo, or the memo as stored if it could not be decoded.
+  pub memo: String,
+  pub height: i64,
+  pub status: BlockStatus,
+  pub timestamp: i64,
+  pub nonce: i64,
+  #[serde(flatten)]
+  pub reason: RejectionReason,
+}
+
+impl RejectedVote {
+  /// Sorts rejections newest first, like votes.
+  pub fn sort(rejected: &mut [RejectedVote]) {
+    rejected.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| a.hash.cmp(&b.hash)));
+  }
+
+  /// Points every superseded vote at the vote which was finally counted for
+  /// its account.
+  pub(crate) fn resolve_superseded(rejected: &mut [RejectedVote], counted_hash: impl Fn(&str) -> Option<String>) {
+    for rejection in rejected {
+      if let RejectionReason::SupersededBy { hash } = &mut rejection.reason {
+        if let Some(counted) = counted_hash(&rejection.account) {
+          *hash = counted;
+        }
+      }
+    }
+  }
+}
+
 impl Vote {
   pub fn new(
     account: impl Into<String>,
@@ -83,35 +139,31 @@ impl Vote {
   }
 
   pub fn match_decoded_memo(&mut self, key: &str) -> Option<String> {
-    if let Ok(decoded) = self.decode_memo() {
-      if decoded.to_lowercase() == key.to_lowercase() || decoded.to_lowercase() == format!("no {}", key.to_lowercase())
-      {
-        return Some(decoded);
-      }
-    }
-    None
+    self.decode_memo().ok().filter(|decoded| is_key_memo(decoded, key))
   }
 
-  pub fn match_decoded_mef_memo(&mut self, round_id: &str, proposal_id: &str) -> Option<String> {
-    if let Ok(decoded) = self.decode_memo() {
-      if decoded.to_lowercase() == format!("mef{} yes {}", round_id, proposal_id)
-        || decoded.to_lowercase() == format!("mef{} no {}", round_id, proposal_id)
-      {
-        return Some(decoded);
-      }
-    }
-    None
+  pub fn match_decoded_mef_memo(&mut self, round_id: usize, proposal_id: usize) -> Option<String> {
+    self
+      .decode_memo()
+      .ok()
+      .filter(|decoded| ConsiderationChoice::classify(decoded, round_id, proposal_id) != ConsiderationChoice::Invalid)
   }
 
-  pub(crate) fn decode_memo(&self) -> Result<String> {
-    let decoded =
-      bs58::decode(&self.memo).into_vec().with_context(|| format!("failed to decode memo {} - bs58", &self.memo))?;
-
-    let value = &decoded[3 .. decoded[2] as usize + 3];
+  pub fn reject(self, reason: RejectionReason) -> RejectedVote {
+    RejectedVote {
+      account: self.account,
+      hash: self.hash,
+      memo: self.memo,
+      height: self.height,
+      status: self.status,
+      timestamp: self.timestamp,
+      nonce: self.nonce,
+      reason,
+    }
+  }
 
-    let result =
-      String::from_utf8(value.to_vec()).with_context(|| format!("failed to decode memo {} - from_utf8", &self.memo))?;
-    Ok(result)
+  pub(crate) fn decode_memo(&self) -> Result<String, MemoError> {
+    decode_memo(&self.memo)
   }
 }
 
@@ -122,73 +174,145 @@ impl From<FetchTransactionResult> for Vote {
 }
 
 impl Wrapper<Vec<Vote>> {
-  pub fn process(self, key: impl Into<String>, tip: i64) -> Wrapper<HashMap<String, Vote>> {
-    let mut map = HashMap::new();
-    let key = key.into();
-
-    for mut vote in self.0 {
-      if let Some(memo) = vote.match_decoded_memo(&key) {
-        vote.update_memo(memo);
+  pub fn process(self, key: impl Into<String>, tip: i64, finality_depth: i64) -> Wrapper<HashMap<String, Vote>> {
+    self.process_with_rejections(key, tip, finality_depth).0
+  }
 
-        if tip - vote.height >= 10 {
-          vote.update_status(BlockStatus::Canonical);
-        }
+  /// Like [`Self::process`], also returning the transactions which were not
+  /// counted.
+  pub fn process_with_rejections(
+    self,
+    key: impl Into<String>,
+    tip: i64,
+    finality_depth: i64,
+  ) -> (Wrapper<HashMap<String, Vote>>, Vec<RejectedVote>) {
+    let key = key.into();
+    self.select(tip, finality_depth, |decoded| is_key_memo(decoded, &key))
+  }
 
-        match map.entry(vote.account.clone()) {
-          Entry::Vacant(e) => {
-            e.insert(vote);
-          }
-          Entry::Occupied(mut e) => {
-            let current_vote = e.get_mut();
-            if vote.is_newer_than(current_vote) {
-              *current_vote = vote;
-            }
-          }
-        }
-      }
-    }
+  pub fn process_mep(
+    self,
+    round_id: usize,
+    proposal_id: usize,
+    tip: i64,
+    finality_depth: i64,
+  ) -> Wrapper<HashMap<String, Vote>> {
+    self.process_mep_with_rejections(round_id, proposal_id, tip, finality_depth).0
+  }
 
-    Wrapper(map)
+  /// Like [`Self::process_mep`], also returning the transactions which were
+  /// not counted.
+  pub fn process_mep_with_rejections(
+    self,
+    round_id: usize,
+    proposal_id: usize,
+    tip: i64,
+    finality_depth: i64,
+  ) -> (Wrapper<HashMap<String, Vote>>, Vec<RejectedVote>) {
+    self.select(tip, finality_depth, |decoded| {
+      ConsiderationChoice::classify(decoded, round_id, proposal_id) != ConsiderationChoice::Invalid
+    })
   }
 
-  pub fn process_mep(self, round_id: usize, proposal_id: usize, tip: i64) -> Wrapper<HashMap<String, Vote>> {
-    let mut map = HashMap::new();
-    let proposal_id_str = proposal_id.to_string();
-    let round_id_str = round_id.to_string();
+  /// Keeps the newest vote of each account among the transactions whose
+  /// decoded memo is accepted.
+  fn select(
+    self,
+    tip: i64,
+    finality_depth: i64,
+    accepts: impl Fn(&str) -> bool,
+  ) -> (Wrapper<HashMap<String, Vote>>, Vec<RejectedVote>) {
+    let mut map: HashMap<String, Vote> = HashMap::new();
+    let mut rejected = Vec::new();
 
     for mut vote in self.0 {
-      if let Some(memo) = vote.match_decoded_mef_memo(&round_id_str, &proposal_id_str) {
-        vote.update_memo(memo);
-
-        if tip - vote.height >= 10 {
-          vote.update_status(BlockStatus::Canonical);
+      if vote.status == BlockStatus::Orphaned {
+        rejected.push(vote.reject(RejectionReason::Orphaned));
+        continue;
+      }
+      let memo = match vote.decode_memo() {
+        Ok(decoded) if accepts(&decoded) => decoded,
+        Ok(decoded) => {
+          vote.update_memo(decoded);
+          rejected.push(vote.reject(RejectionReason::WrongKey));
+          continue;
         }
+        Err(_) => {
+          rejected.push(vote.reject(RejectionReason::UndecodableMemo));
+          continue;
+        }
+      };
+      vote.update_memo(memo);
 
-        match map.entry(vote.account.clone()) {
-          Entry::Vacant(e) => {
-            e.insert(vote);
-          }
-          Entry::Occupied(mut e) => {
-            let current_vote = e.get_mut();
-            if vote.is_newer_than(current_vote) {
-              *current_vote = vote;
-            }
+      if vote.status == BlockStatus::Pending && tip - vote.height >= finality_depth {
+        vote.update_status(BlockStatus::Canonical);
+      }
+
+      match map.entry(vote.account.clone()) {
+        Entry::Vacant(e) => {
+          e.insert(vote);
+        }
+        Entry::Occupied(mut e) => {
+          let current_vote = e.get_mut();
+          if vote.is_newer_than(current_vote) {
+            let superseded = std::mem::replace(current_vote, vote);
+            rejected.push(superseded.reject(RejectionReason::SupersededBy { hash: String::new() }));
+          } else {
+            rejected.push(vote.reject(RejectionReason::SupersededBy { hash: String::new() }));
           }
         }
       }
     }
 
-    Wrapper(map)
+    RejectedVote::resolve_superseded(&mut rejected, |account| map.get(account).map(|vote| vote.hash.clone()));
+    (Wrapper(map), rejected)
   }
 
-  pub fn into_weighted(self, proposal: &Proposal, ledger: &Ledger, tip: i64) -> Wrapper<Vec<VoteWithWeight>> {
-    let votes = self.process(&proposal.key, tip);
+  pub fn into_weighted(
+    self,
+    proposal: &Proposal,
+    ledger: &Ledger,
+    tip: i64,
+    finality_depth: i64,
+  ) -> Wrapper<Vec<VoteWithWeight>> {
+    self.process(&proposal.key, tip, finality_depth).weighted(&proposal.version, ledger)
+  }
 
-    let votes_with_stake: Vec<VoteWithWeight> = votes
+  pub fn into_weighted_mep(
+    self,
+    round_id: usize,
+    proposal_id: usize,
+    ledger: &Ledger,
+    tip: i64,
+    finality_depth: i64,
+  ) -> Wrapper<Vec<VoteWithWeight>> {
+    self.process_mep(round_id, proposal_id, tip, finality_depth).weighted_mep(ledger)
+  }
+}
+
+impl Wrapper<HashMap<String, Vote>> {
+  pub fn to_vec(&self) -> Wrapper<Vec<Vote>> {
+    Wrapper(self.0.values().cloned().collect())
+  }
+
+  pub fn weighted(&self, version: &ProposalVersion, ledger: &Ledger) -> Wrapper<Vec<VoteWithWeight>> {
+    if *version == ProposalVersion::V3 {
+      let allocation = ledger.allocate_stake(self);
+      return Wrapper(
+        self
+          .0
+          .iter()
+          .filter(|(account, _)| ledger.account(account).is_some())
+          .map(|(account, vote)| vote.to_weighted(allocation.get(account).copied().unwrap_or_default()))
+          .collect(),
+      );
+    }
+
+    let votes_with_stake: Vec<VoteWithWeight> = self
       .0
       .iter()
       .filter_map(|(account, vote)| {
-        let stake = ledger.get_stake_weight(&votes, &proposal.version, account).ok()?;
+        let stake = ledger.get_stake_weight(self, version, account).ok()?;
         Some(vote.to_weighted(stake))
       })
       .collect();
@@ -196,20 +320,43 @@ impl Wrapper<Vec<Vote>> {
     Wrapper(votes_with_stake)
   }
 
-  pub fn into_weighted_mep(
-    self,
-    round_id: usize,
-    proposal_id: usize,
-    ledger: &Ledger,
-    tip: i64,
-  ) -> Wrapper<Vec<VoteWithWeight>> {
-    let votes = self.process_mep(round_id, proposal_id, tip);
+  /// The votes which [`Self::weighted`] drops or weights with nothing
+  /// because of the voter's ledger account.
+  pub fn rejected_by_ledger(&self, version: &ProposalVersion, ledger: &Ledger) -> Vec<RejectedVote> {
+    self.rejected_by_account(ledger, matches!(version, ProposalVersion::V1))
+  }
+
+  /// The votes which [`Self::weighted_mep`] drops or weights with nothing
+  /// because of the voter's ledger account.
+  pub fn rejected_by_ledger_mep(&self, ledger: &Ledger) -> Vec<RejectedVote> {
+    self.rejected_by_account(ledger, true)
+  }
+
+  fn rejected_by_account(&self, ledger: &Ledger, delegating_has_no_weight: bool) -> Vec<RejectedVote> {
+    self
+      .0
+      .values()
+      .filter_map(|vote| {
+        let reason = match ledger.account(&vote.account) {
+          None => RejectionReason::NotInLedger,
+          Some(account) => match &account.delegate {
+            Some(delegate) if delegating_has_no_weight && *delegate != account.pk => {
+              RejectionReason::DelegatedAway { delegate: delegate.clone() }
+            }
+            _ => return None,
+          },
+        };
+        Some(vote.clone().reject(reason))
+      })
+      .collect()
+  }
 
-    let votes_with_stake: Vec<VoteWithWeight> = votes
+  pub fn weighted_mep(&self, ledger: &Ledger) -> Wrapper<Vec<VoteWithWeight>> {
+    let votes_with_stake: Vec<VoteWithWeight> = self
       .0
       .iter()
       .filter_map(|(account, vote)| {
-        let stake = ledger.get_stake_weight_mep(&votes, account).ok()?;
+        let stake = ledger.get_stake_weight_mep(account).ok()?;
         Some(vote.to_weighted(stake))
       })
       .collect();
@@ -218,9 +365,90 @@ impl Wrapper<Vec<Vote>> {
   }
 }
 
-impl Wrapper<HashMap<String, Vote>> {
-  pub fn to_vec(&self) -> Wrapper<Vec<Vote>> {
-    Wrapper(self.0.values().cloned().collect())
+/// Whether a decoded memo is a vote for or against the proposal `key`.
+fn is_key_memo(decoded: &str, key: &str) -> bool {
+  VoteMemo::parse(decoded).is_some_and(|memo| memo.is_binary_vote_on(key))
+}
+
+/// The processed votes of a proposal, along with how far the archive has been
+/// read for them.
+///
+/// `cursor` is a block height at or below which every block is final, so
+/// later reads only need to fetch the blocks above it. Blocks are read by
+/// height rather than timestamp, as a block can reach the archive after
+/// blocks with later timestamps.
+#[derive(Debug, Clone, Default)]
+pub struct VoteIngestion {
+  pub votes: HashMap<String, Vote>,
+  pub cursor: Option<i64>,
+  /// The raw transactions the votes were read from.
+  pub transactions: Vec<FetchTransactionResult>,
+  /// The chain tip the votes were last processed at.
+  pub chain_tip: Option<i64>,
+}
+
+impl VoteIngestion {
+  /// The height above which blocks still have to be read from the archive.
+  pub fn read_above(&self) -> i64 {
+    self.cursor.unwrap_or(0)
+  }
+
+  /// Whether a vote which is not yet final is missing from a re-read of the
+  /// blocks above `read_above`, meaning its block has been orphaned.
+  ///
+  /// The account's previous vote is not retained once superseded, so the
+  /// votes have to be ingested again from scratch when this happens.
+  pub fn has_orphaned_transactions(&self, fetched_hashes: &HashSet<String>, read_above: i64) -> bool {
+    self
+      .transactions
+      .iter()
+      .any(|transaction| transaction.height > read_above && !fetched_hashes.contains(&transaction.hash))
+  }
+
+  /// Replaces the transactions read from the blocks above `read_above` with
+  /// a new read of those blocks. Returns whether the transactions or the
+  /// chain tip changed, in which case votes processed before are stale.
+  pub fn record(&mut self, read_above: i64, transactions: &[FetchTransactionResult], chain_tip: i64) -> bool {
+    let (final_transactions, previous): (Vec<_>, Vec<_>) =
+      std::mem::take(&mut self.transactions).into_iter().partition(|transaction| transaction.height <= read_above);
+    let changed = self.chain_tip != Some(chain_tip)
+      || previous.len() != transactions.len()
+      || transactions.iter().any(|transaction| !previous.contains(transaction));
+    self.transactions = final_transactions;
+    self.transactions.extend_from_slice(transactions);
+    self.chain_tip = Some(chain_tip);
+    changed
+  }
+
+  /// Merges newly processed votes, keeping the newest vote of each account,
+  /// and advances the cursor to the last final block.
+  pub fn merge(&mut self, delta: Wrapper<HashMap<String, Vote>>, tip: i64, finality_depth: i64) {
+    for (account, vote) in delta.0 {
+      match self.votes.entry(account) {
+        Entry::Vacant(e) => {
+          e.insert(vote);
+        }
+        Entry::Occupied(mut e) => {
+          let current_vote = e.get_mut();
+          if vote.hash == current_vote.hash || vote.is_newer_than(current_vote) {
+            *current_vote = vote;
+          }
+        }
+      }
+    }
+
+    for vote in self.votes.values_mut() {
+      if vote.status == BlockStatus::Pending && tip - vote.height >= finality_depth {
+        vote.update_status(BlockStatus::Canonical);
+      }
+    }
+
+    self.advance(tip, finality_depth);
+  }
+
+  /// Advances the cursor to the last block final at `tip`.
+  pub fn advance(&mut self, tip: i64, finality_depth: i64) {
+    self.cursor = self.cursor.max(Some(tip - finality_depth));
   }
 }
 
@@ -231,8 +459,10 @@ impl Wrapper<HashMap<String, Vote>> {
   }
 }
 impl Wrapper<Vec<VoteWithWeight>> {
+  /// Sorts the votes newest first, breaking ties by account so that the order
+  /// does not depend on how the votes were collected.
   pub fn sort_by_timestamp(mut self) -> Self {
-    self.0.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    self.0.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| a.account.cmp(&b.account)));
     self
   }
 }
@@ -240,6 +470,7 @@ impl Wrapper<Vec<VoteWithWeight>> {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::LedgerAccount;
 
   #[test]
   fn test_decode_memo() {
@@ -279,7 +510,7 @@ mod tests {
   #[test]
   fn test_process_votes() {
     let votes = get_test_votes();
-    let binding = Wrapper(votes).process("cftest-2", 129);
+    let binding = Wrapper(votes).process("cftest-2", 129, 10);
     let processed = binding.to_vec().0;
 
     assert_eq!(processed.len(), 2);
@@ -302,6 +533,135 @@ mod tests {
     assert_eq!(a2.nonce, 2);
   }
 
+  #[test]
+  fn test_process_votes_with_rejections() {
+    let yes = "E4YdLeukpqzqyBAxujeELx9SZWoUW9MhcUfnGHF9PhQmxTJcpmj7j";
+    let votes = vec![
+      Vote::new("A", "a2", yes, 102, BlockStatus::Canonical, 102, 2),
+      Vote::new("A", "a1", yes, 101, BlockStatus::Canonical, 101, 1),
+      Vote::new("A", "a3", yes, 103, BlockStatus::Canonical, 103, 3),
+      Vote::new(
+        "A",
+        "a4",
+        "E4Yf92G48v8FApR4EWQq3iKb2vZkHHxZHPaZ73NQNBXmHeXNzHHSp",
+        104,
+        BlockStatus::Canonical,
+        104,
+        4,
+      ),
+      Vote::new("B", "b1", "not a memo!", 105, BlockStatus::Canonical, 105, 1),
+      Vote::new("C", "c1", yes, 106, BlockStatus::Canonical, 106, 1),
+      Vote::new("F", "f1", yes, 107, BlockStatus::Canonical, 107, 1),
+    ];
+    let (counted, mut rejected) = Wrapper(votes).process_with_rejections("cftest-2", 129, 10);
+    assert_eq!(counted.0.len(), 3);
+    assert_eq!(counted.0["A"].hash, "a3");
+
+    RejectedVote::sort(&mut rejected);
+    let reasons: Vec<_> = rejected.iter().map(|r| (r.hash.as_str(), r.reason.clone())).collect();
+    let superseded = RejectionReason::SupersededBy { hash: "a3".to_string() };
+    assert_eq!(reasons, vec![
+      ("b1", RejectionReason::UndecodableMemo),
+      ("a4", RejectionReason::WrongKey),
+      ("a2", superseded.clone()),
+      ("a1", superseded),
+    ]);
+    assert_eq!(rejected[1].memo, "Payment#0");
+
+    let ledger = Ledger::new(vec![
+      LedgerAccount::new("A".to_string(), "1".to_string(), None),
+      LedgerAccount::new("C".to_string(), "1".to_string(), Some("A".to_string())),
+    ]);
+    let mut by_ledger = counted.rejected_by_ledger(&ProposalVersion::V1, &ledger);
+    RejectedVote::sort(&mut by_ledger);
+    let reasons: Vec<_> = by_ledger.into_iter().map(|r| (r.hash, r.reason)).collect();
+    assert_eq!(reasons, vec![
+      ("f1".to_string(), RejectionReason::NotInLedger),
+      ("c1".to_string(), RejectionReason::DelegatedAway { delegate: "A".to_string() }),
+    ]);
+    let by_ledger = counted.rejected_by_ledger(&ProposalVersion::V2, &ledger);
+    assert_eq!(by_ledger.len(), 1);
+    assert_eq!(by_ledger[0].reason, RejectionReason::NotInLedger);
+  }
+
+  #[test]
+  fn test_vote_ingestion_merge() {
+    let mut ingestion = VoteIngestion::default();
+    assert_eq!(ingestion.read_above(), 0);
+
+    ingestion.merge(Wrapper(get_test_votes()).process("cftest-2", 125, 10), 125, 10);
+    assert_eq!(ingestion.votes.len(), 2);
+    assert_eq!(ingestion.votes["1"].status, BlockStatus::Canonical);
+    assert_eq!(ingestion.votes["2"].status, BlockStatus::Pending);
+    // Blocks up to 115 are final at a tip of 125.
+    assert_eq!(ingestion.cursor, Some(115));
+    assert_eq!(ingestion.read_above(), 115);
+
+    // The pending vote is read again and picks up its new status.
+    let mut refetched = Vote::new("2", "4", "cftest-2", 120, BlockStatus::Canonical, 120, 2);
+    let newer = Vote::new("3", "5", "cftest-2", 130, BlockStatus::Pending, 130, 1);
+    let mut delta = HashMap::new();
+    delta.insert("2".to_string(), refetched.clone());
+    delta.insert("3".to_string(), newer);
+    ingestion.merge(Wrapper(delta), 135, 10);
+    refetched.update_status(BlockStatus::Canonical);
+    assert_eq!(ingestion.votes["2"], refetched);
+    assert_eq!(ingestion.votes.len(), 3);
+    assert_eq!(ingestion.cursor, Some(125));
+
+    // An older vote does not replace a newer one.
+    let mut delta = HashMap::new();
+    delta.insert("3".to_string(), Vote::new("3", "6", "no cftest-2", 125, BlockStatus::Pending, 125, 1));
+    ingestion.merge(Wrapper(delta), 135, 10);
+    assert_eq!(ingestion.votes["3"].hash, "5");
+
+    // The cursor does not move back if the tip does.
+    ingestion.merge(Wrapper(HashMap::new()), 130, 10);
+    assert_eq!(ingestion.cursor, Some(125));
+  }
+
+  #[test]
+  fn test_vote_ingestion_record() {
+    let transaction = |hash: &str, height| FetchTransactionResult {
+      account: "1".to_string(),
+      hash: hash.to_string(),
+      memo: "cftest-2".to_string(),
+      height,
+      status: BlockStatus::Pending,
+      timestamp: height,
+      nonce: 1,
+    };
+    let mut ingestion = VoteIngestion::default();
+    assert!(ingestion.record(0, &[transaction("1", 100), transaction("2", 120)], 125));
+    ingestion.advance(125, 10);
+    let read_above = ingestion.read_above();
+    assert_eq!(read_above, 115);
+
+    // The same read at the same tip changes nothing, a new tip does.
+    assert!(!ingestion.record(read_above, &[transaction("2", 120)], 125));
+    assert!(ingestion.record(read_above, &[transaction("2", 120)], 126));
+    assert_eq!(ingestion.transactions.len(), 2);
+
+    // The final transaction is not re-checked, the pending one is.
+    let fetched = HashSet::from(["2".to_string()]);
+    assert!(!ingestion.has_orphaned_transactions(&fetched, read_above));
+    assert!(ingestion.has_orphaned_transactions(&HashSet::new(), read_above));
+    assert!(!ingestion.has_orphaned_transactions(&HashSet::new(), 120));
+
+    // A transaction missing from a re-read is dropped.
+    assert!(ingestion.record(read_above, &[], 126));
+    assert_eq!(ingestion.transactions, vec![transaction("1", 100)]);
+  }
+
+  #[test]
+  fn test_process_rejects_orphaned_votes() {
+    let mut votes = get_test_votes();
+    votes[3].update_status(BlockStatus::Orphaned);
+    let (counted, rejected) = Wrapper(votes).process_with_rejections("cftest-2", 125, 10);
+    assert_eq!(counted.0["2"].hash, "3");
+    assert!(rejected.iter().any(|r| r.hash == "4" && r.reason == RejectionReason::Orphaned));
+  }
+
   fn get_test_votes() -> Vec<Vote> {
     vec![
       Vote::new("1", "1", "E4YjFkHVUXbEAkQcUrAEcS1fqvbncnn9Tuz2Jtb1Uu79zY9UAJRpd", 100, BlockStatus::Pending, 100, 1),
@@ -328,8 +688,8 @@ mod tests {
 
   #[test]
   fn test_match_decode_mep_memo() {
-    let round_id = "1";
-    let proposal_id = "1";
+    let round_id = 1;
+    let proposal_id = 1;
     let mut votes = get_test_mep_votes();
 
     let v0_decoded = votes[0].match_decoded_mef_memo(round_id, proposal_id).unwrap();
@@ -360,7 +720,7 @@ mod tests {
   #[test]
   fn test_process_mep_votes() {
     let votes = get_test_mep_votes();
-    let binding = Wrapper(votes).process_mep(1, 1, 130);
+    let binding = Wrapper(votes).process_mep(1, 1, 130, 10);
     let processed = binding.to_vec().0;
 
     assert_eq!(processed.len(), 11);
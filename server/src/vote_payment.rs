@@ -0,0 +1,77 @@
+use anyhow::{Result, anyhow, ensure};
+use serde::Serialize;
+
+use crate::VoteMemo;
+
+const PUBLIC_KEY_VERSION_BYTE: u8 = 0xcb;
+const PUBLIC_KEY_LENGTH: usize = 36;
+
+/// An unsigned self-payment casting a vote, as accepted by signers such as
+/// mina-signer's `signPayment`. Amounts are in nanomina.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VotePayment {
+  pub from: String,
+  pub to: String,
+  pub amount: String,
+  pub fee: String,
+  pub nonce: String,
+  /// The canonical text of the vote, which signers encode into the memo.
+  pub memo: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub valid_until: Option<String>,
+}
+
+impl VotePayment {
+  pub fn new(sender: &str, vote: &VoteMemo, fee: u64, nonce: u32, valid_until: Option<u32>) -> Result<Self> {
+    let decoded = bs58::decode(sender)
+      .with_check(Some(PUBLIC_KEY_VERSION_BYTE))
+      .into_vec()
+      .map_err(|_| anyhow!("{sender} is not a public key"))?;
+    ensure!(decoded.len() == PUBLIC_KEY_LENGTH, "{sender} is not a public key");
+    // Rejects votes which do not fit in a memo.
+    vote.to_bytes()?;
+
+    Ok(VotePayment {
+      from: sender.to_string(),
+      to: sender.to_string(),
+      amount: "0".to_string(),
+      fee: fee.to_string(),
+      nonce: nonce.to_string(),
+      memo: vote.to_string(),
+      valid_until: valid_until.map(|slot| slot.to_string()),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SENDER: &str = "B62qrPN5Y5yq8kGE3FbVKbGTdTAJNdtNtB5sNVpxyRwWGcDEhpMzc8g";
+
+  #[test]
+  fn test_vote_payment() {
+    let vote = VoteMemo::parse("MEF 1 3 5 2").unwrap();
+    let payment = VotePayment::new(SENDER, &vote, 10_000_000, 3, None).unwrap();
+    assert_eq!(
+      serde_json::to_value(&payment).unwrap(),
+      serde_json::json!({
+        "from": SENDER,
+        "to": SENDER,
+        "amount": "0",
+        "fee": "10000000",
+        "nonce": "3",
+        "memo": "mef 1 3 5 2",
+      })
+    );
+
+    let payment = VotePayment::new(SENDER, &vote, 10_000_000, 3, Some(1000)).unwrap();
+    assert_eq!(payment.valid_until.as_deref(), Some("1000"));
+
+    assert!(VotePayment::new(&SENDER[.. SENDER.len() - 1], &vote, 10_000_000, 3, None).is_err());
+    assert!(VotePayment::new("E4Yf92G48v8FApR4EWQq3iKb2vZkHHxZHPaZ73NQNBXmHeXNzHHSp", &vote, 0, 0, None).is_err());
+    let long = VoteMemo::Binary { key: "a".repeat(40), yes: false };
+    assert!(VotePayment::new(SENDER, &long, 10_000_000, 3, None).is_err());
+  }
+}